@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Open a URL in the configured browser, or the user's default one.
+///
+/// `browser` is a command line (split on whitespace) that gets the URL as its
+/// last argument. Without one, honors `$BROWSER` and falls back to the platform
+/// default (`xdg-open` and friends on Linux, `open` on macOS, the shell
+/// handler on Windows). `private` opens a private/incognito window, which
+/// needs a known browser named in `browser` or `$BROWSER`.
+pub fn open_url(url: &str, browser: Option<&str>, private: bool) -> Result<()> {
+    // The system default can't be asked for a private window, so name one
+    let from_env;
+    let browser = match browser {
+        Some(browser) => browser,
+        None if private => {
+            from_env = std::env::var("BROWSER").unwrap_or_default();
+            // $BROWSER may list several browsers separated by colons
+            from_env.split(':').next().unwrap_or_default()
+        }
+        None => return webbrowser::open(url).context("Failed to launch browser"),
+    };
+
+    let mut words = browser.split_whitespace();
+    let program = words.next().context(if private {
+        "incognito needs a browser: set `browser` in the config or $BROWSER"
+    } else {
+        "The browser setting is empty"
+    })?;
+    let mut command = Command::new(program);
+    command.args(words);
+    if private {
+        command.arg(private_window_flag(program)?);
+    }
+    // Not waited on: some browsers only return once their window closes
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to launch browser `{}`", browser))?;
+    Ok(())
+}
+
+/// The flag that opens a private window in `program`'s browser family
+fn private_window_flag(program: &str) -> Result<&'static str> {
+    let name = Path::new(program)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if ["firefox", "librewolf", "waterfox", "floorp"]
+        .iter()
+        .any(|family| name.contains(family))
+    {
+        Ok("--private-window")
+    } else if name.contains("edge") {
+        Ok("--inprivate")
+    } else if ["chrom", "brave", "vivaldi"]
+        .iter()
+        .any(|family| name.contains(family))
+    {
+        Ok("--incognito")
+    } else {
+        anyhow::bail!(
+            "Don't know how to open a private window in `{}`; put its flag in `browser` instead",
+            program
+        )
+    }
+}
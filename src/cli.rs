@@ -0,0 +1,119 @@
+use clap::builder::PossibleValuesParser;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+/// TUI for switching between Tailscale tailnets
+#[derive(Debug, Parser)]
+#[command(name = "tailswitch", version, about)]
+pub struct Cli {
+    /// Log tailscale commands and their timings to stderr (-vv for their
+    /// output too); the TUI logs to a temp file instead. RUST_LOG overrides.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Disable colors (also enabled by setting NO_COLOR)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Print the tailscale commands that would change anything instead of running them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Check that every configured login_server accepts connections before starting
+    #[arg(long, global = true)]
+    pub check_servers: bool,
+
+    /// Use this config file instead of ~/.config/tailswitch/config.toml
+    #[arg(long, global = true, value_name = "PATH", env = "TAILSWITCH_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Switch to a tailnet without launching the TUI
+    Switch {
+        /// Tailnet name (existing profile or configured tailnet), or a profile
+        /// ID from `tailscale switch --list`
+        name: String,
+    },
+    /// List existing profiles and configured tailnets
+    List {
+        /// Print as a JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Apply a tailnet's configured flags with `tailscale up`
+    Up {
+        /// Tailnet name (defaults to the current tailnet)
+        name: Option<String>,
+    },
+    /// Print the connection status, e.g. for a status bar module
+    Status {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = StatusFormat::Text)]
+        format: StatusFormat,
+    },
+    /// Print the active tailnet's name (exits 1 when not connected), for
+    /// shell prompts and tmux status lines
+    Current,
+    /// Log out of the current profile
+    Logout,
+    /// Add existing tailscale profiles that aren't in the config yet
+    Import,
+    /// Rename a tailscale profile (the name shown in place of its account)
+    Rename {
+        /// Profile to rename: its tailnet name or profile ID
+        profile: String,
+        /// The new name
+        name: String,
+    },
+    /// Make yourself tailscale's operator (one sudo prompt), so tailswitch no
+    /// longer needs sudo
+    SetOperator,
+    /// Check the tailscale install, daemon, permissions, config and profiles
+    Doctor,
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatusFormat {
+    /// One line, e.g. "Work · Running · 100.64.0.1 · 3/5 peers online"
+    Text,
+    /// The parsed status as a JSON object
+    Json,
+    /// `{"text", "tooltip", "class"}` for a waybar custom module
+    Waybar,
+}
+
+/// Print a completion script, offering the given tailnet names wherever a
+/// subcommand takes a tailnet `name` argument
+pub fn print_completions(shell: Shell, tailnet_names: Vec<String>) {
+    let mut cmd = Cli::command();
+
+    let targets: Vec<String> = cmd
+        .get_subcommands()
+        .filter(|sub| sub.get_arguments().any(|arg| arg.get_id() == "name"))
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+
+    // Only used for generating the script - the real parser accepts any name
+    if !tailnet_names.is_empty() {
+        for target in targets {
+            cmd = cmd.mut_subcommand(target, |sub| {
+                sub.mut_arg("name", |arg| {
+                    arg.value_parser(PossibleValuesParser::new(tailnet_names.clone()))
+                })
+            });
+        }
+    }
+
+    clap_complete::generate(shell, &mut cmd, "tailswitch", &mut std::io::stdout());
+}
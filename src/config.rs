@@ -1,80 +1,884 @@
+use crate::runner;
+use crate::tailscale;
+use crate::theme::{THEME_NAMES, Theme};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+/// How long `check_login_servers` waits for each server to accept a connection
+const SERVER_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often the TUI re-fetches status in the background, unless configured
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Warn this many days before the node key expires, unless configured
+const DEFAULT_KEY_EXPIRY_WARN_DAYS: u64 = 7;
+
+/// How long to wait for the connection after switching, unless configured
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)] // so typos in key names are reported, not ignored
 pub struct Config {
+    /// Path to the tailscale binary, if it isn't `tailscale` on PATH
+    pub tailscale_path: Option<String>,
+    /// Show a desktop notification after switching (default: on for Linux/macOS)
+    pub notifications: Option<bool>,
+    /// Flags for `tailscale up`/`login` shared by every tailnet
+    pub default_flags: Option<Vec<String>>,
+    /// Shell command run after every successful switch, unless the tailnet has its own
+    pub post_switch: Option<String>,
+    /// Browser for login pages, unless the tailnet has its own (default: `$BROWSER`)
+    pub browser: Option<String>,
+    /// Open login pages in a private window, unless the tailnet says otherwise
+    pub incognito: Option<bool>,
+    /// Seconds a tailscale command whose output is read may run before it's
+    /// killed (0 disables). Commands on the terminal, which may wait for the
+    /// user (`tailscale up`, a sudo prompt), have no limit.
+    pub command_timeout_secs: Option<u64>,
+    /// Color theme for the TUI: "default", "mono" or "solarized"
+    pub theme: Option<String>,
+    /// Days before the node key expires to start warning (0 disables)
+    pub key_expiry_warn_days: Option<u64>,
+    /// Seconds to wait for the connection to come up after switching (0: check once)
+    pub connect_timeout_secs: Option<u64>,
+    /// Seconds between background status refreshes in the TUI (0: only on `r`)
+    pub refresh_interval_secs: Option<u64>,
+    /// Directory `w` saves the output view to (default: the current directory)
+    pub output_dir: Option<String>,
     pub tailnets: Vec<Tailnet>,
+    /// File this config was loaded from and is saved back to
+    #[serde(skip)]
+    path: PathBuf,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Tailnet {
     pub name: String,
+    /// Friendlier label shown in the TUI; tailscale commands still use `name`
+    pub display_name: Option<String>,
+    /// Section of the TUI list this tailnet is shown under, e.g. "Work"
+    pub group: Option<String>,
     pub login_server: Option<String>,
     pub auth_key: Option<String>,
+    /// File containing the auth key, read each time it's needed
+    pub auth_key_file: Option<String>,
+    /// Shell command that prints the auth key, e.g. `pass show ts/work`
+    pub auth_key_command: Option<String>,
     pub flags: Option<Vec<String>>,
+    /// Subnets to offer to the tailnet, e.g. "10.0.0.0/24" (`--advertise-routes`)
+    pub advertise_routes: Option<Vec<String>>,
+    /// Offer this node as an exit node (`--advertise-exit-node`)
+    pub advertise_exit_node: Option<bool>,
+    /// Shell command run after switching to this tailnet
+    pub post_switch: Option<String>,
+    /// Browser to open this tailnet's login page in, e.g. `firefox -P work`
+    pub browser: Option<String>,
+    /// Open the login page in a private window, so no other SSO session is reused
+    pub incognito: Option<bool>,
+    /// Profile to switch to when several accounts share this tailnet name.
+    /// Set from the list, never saved.
+    #[serde(skip)]
+    pub profile_id: Option<String>,
 }
 
 impl Config {
-    pub fn load() -> Result<Self> {
-        let config_path = Self::config_path()?;
+    /// Load the config from an explicit path (`--config` / `TAILSWITCH_CONFIG`),
+    /// or from the default location, creating it there if it doesn't exist yet
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let config_path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default_path = Self::default_path()?;
+                if !default_path.exists() {
+                    // Create default config
+                    let default_config = Self {
+                        path: default_path,
+                        ..Self::default()
+                    };
+                    default_config.save()?;
+                    return Ok(default_config);
+                }
+                default_path
+            }
+        };
+        Self::read(config_path)
+    }
 
-        if !config_path.exists() {
-            // Create default config
-            let default_config = Self::default();
-            default_config.save()?;
-            return Ok(default_config);
+    /// Load the config without creating it: `None` when there's none at the
+    /// default location yet. For checks that mustn't change anything.
+    pub fn load_existing(path: Option<&Path>) -> Result<Option<Self>> {
+        let config_path = match path {
+            Some(path) => path.to_path_buf(),
+            None => Self::default_path()?,
+        };
+        if path.is_none() && !config_path.exists() {
+            return Ok(None);
         }
+        Self::read(config_path).map(Some)
+    }
+
+    fn read(config_path: PathBuf) -> Result<Self> {
+        let contents = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read config file {}", config_path.display()))?;
 
-        let contents = fs::read_to_string(&config_path).context("Failed to read config file")?;
+        let mut config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", config_path.display()))?;
+        config.path = config_path;
 
-        let config: Config = toml::from_str(&contents).context("Failed to parse config file")?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config file {}", config.path.display()))?;
 
         Ok(config)
     }
 
+    /// Write the config atomically: a temp file in the same directory is
+    /// renamed over the old one, which is kept as `config.toml.bak`
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path()?;
-
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).context("Failed to create config directory")?;
-        }
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir).context("Failed to create config directory")?;
 
         let contents = toml::to_string_pretty(self).context("Failed to serialize config")?;
 
-        fs::write(&config_path, contents).context("Failed to write config file")?;
+        // Merge into the existing file so hand-written comments and formatting survive
+        let existing = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|existing| existing.parse::<DocumentMut>().ok());
+        let contents = match existing {
+            Some(mut document) => {
+                let updated: DocumentMut =
+                    contents.parse().context("Failed to serialize config")?;
+                merge_table(document.as_table_mut(), updated.as_table());
+                document.to_string()
+            }
+            None => contents,
+        };
+
+        let mut file = tempfile::NamedTempFile::new_in(dir)
+            .context("Failed to create temporary config file")?;
+        file.write_all(contents.as_bytes())
+            .and_then(|()| file.as_file().sync_all())
+            .context("Failed to write config file")?;
+
+        if self.path.exists() {
+            let mut backup = self.path.clone().into_os_string();
+            backup.push(".bak");
+            fs::copy(&self.path, backup).context("Failed to back up config file")?;
+        }
+
+        file.persist(&self.path)
+            .context("Failed to replace config file")?;
 
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf> {
+    /// Where the config lives unless `--config` / `TAILSWITCH_CONFIG` says otherwise
+    pub fn default_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir().context("Failed to get config directory")?;
         Ok(config_dir.join("tailswitch").join("config.toml"))
     }
 
-    pub fn get_config_path_string() -> Result<String> {
-        Ok(Self::config_path()?.to_string_lossy().to_string())
+    /// Catch mistakes deserializing can't, naming the offending tailnet. Also
+    /// checks for unset variables up front rather than halfway through a switch.
+    pub fn validate(&self) -> Result<()> {
+        for flag in self.default_flags.iter().flatten() {
+            expand_env(flag, "default_flags")?;
+        }
+        if let Some(ref dir) = self.output_dir {
+            expand_env(dir, "output_dir")?;
+        }
+
+        if let Some(ref theme) = self.theme
+            && Theme::named(theme).is_none()
+        {
+            anyhow::bail!(
+                "theme '{}' is not a built-in theme; expected one of: {}",
+                theme,
+                THEME_NAMES.join(", ")
+            );
+        }
+
+        let mut seen = HashSet::new();
+
+        for (index, tailnet) in self.tailnets.iter().enumerate() {
+            let name = tailnet.name.trim();
+            if name.is_empty() {
+                anyhow::bail!("tailnets[{}]: name must not be empty", index);
+            }
+            let label = format!("tailnets[{}] ('{}')", index, name);
+
+            if !seen.insert(name) {
+                anyhow::bail!("{}: another tailnet already uses this name", label);
+            }
+            // Aliases share the namespace, so `switch <name>` stays unambiguous
+            if let Some(display_name) = tailnet.display_name.as_deref().map(str::trim)
+                && display_name != name
+                && !seen.insert(display_name)
+            {
+                anyhow::bail!(
+                    "{}: display_name '{}' is already used by another tailnet",
+                    label,
+                    display_name
+                );
+            }
+
+            if tailnet.auth_key_sources() > 1 {
+                anyhow::bail!(
+                    "{}: only one of auth_key, auth_key_file and auth_key_command may be set",
+                    label
+                );
+            }
+
+            for route in tailnet.advertise_routes.iter().flatten() {
+                if let Err(e) = parse_cidr(route) {
+                    anyhow::bail!(
+                        "{}: advertise_routes entry '{}' {}; expected a subnet like 10.0.0.0/24",
+                        label,
+                        route,
+                        e
+                    );
+                }
+            }
+
+            let expanded = tailnet.expanded()?;
+            if let Some(ref server) = expanded.login_server
+                && let Err(e) = parse_http_url(server)
+            {
+                anyhow::bail!(
+                    "{}: login_server '{}' {}; expected an http(s) URL, e.g. https://headscale.example.com",
+                    label,
+                    server,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Try a TCP connection to each configured login server, so an unreachable
+    /// server is reported by name instead of as a tailscale login timeout
+    pub fn check_login_servers(&self) -> Result<()> {
+        for tailnet in &self.tailnets {
+            let Some(server) = tailnet.expanded()?.login_server else {
+                continue;
+            };
+            let (host, port) = parse_http_url(&server)
+                .map_err(|e| anyhow::anyhow!("'{}': login_server {}", tailnet.name, e))?;
+
+            let addresses: Vec<_> = (host.as_str(), port)
+                .to_socket_addrs()
+                .with_context(|| {
+                    format!(
+                        "'{}': could not resolve login_server {}",
+                        tailnet.name, server
+                    )
+                })?
+                .collect();
+            let reachable = addresses
+                .iter()
+                .any(|address| TcpStream::connect_timeout(address, SERVER_CHECK_TIMEOUT).is_ok());
+
+            if !reachable {
+                anyhow::bail!(
+                    "'{}': login_server {} is not reachable",
+                    tailnet.name,
+                    server
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Settings to use for the named tailnet: its config entry (or just the
+    /// name if there is none) with environment variables expanded and
+    /// `default_flags` and the global `post_switch` merged in
+    pub fn resolve_tailnet(&self, name: &str) -> Result<Tailnet> {
+        let mut tailnet = match self.tailnets.iter().find(|t| t.name == name) {
+            Some(tailnet) => tailnet.expanded()?,
+            None => Tailnet {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        };
+
+        if tailnet.post_switch.is_none() {
+            tailnet.post_switch = self.post_switch.clone();
+        }
+        if tailnet.browser.is_none() {
+            tailnet.browser = self.browser.clone();
+        }
+        if tailnet.incognito.is_none() {
+            tailnet.incognito = self.incognito;
+        }
+
+        if let Some(ref defaults) = self.default_flags {
+            let defaults = defaults
+                .iter()
+                .map(|flag| expand_env(flag, "default_flags"))
+                .collect::<Result<Vec<_>>>()?;
+            let merged = merge_flags(&defaults, tailnet.flags.as_deref().unwrap_or_default());
+            tailnet.flags = (!merged.is_empty()).then_some(merged);
+        }
+
+        // The structured settings win over the same flags written out by hand
+        let advertise = tailnet.advertise_flags();
+        if !advertise.is_empty() {
+            tailnet.flags = Some(merge_flags(
+                tailnet.flags.as_deref().unwrap_or_default(),
+                &advertise,
+            ));
+        }
+
+        Ok(tailnet)
+    }
+
+    /// Groups in the order they first appear, empty when none are configured
+    pub fn groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = Vec::new();
+        for group in self.tailnets.iter().filter_map(|t| t.group.as_deref()) {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    /// The real tailnet name for `name`, which may be a `display_name` alias
+    pub fn resolve_alias(&self, name: &str) -> String {
+        self.tailnets
+            .iter()
+            .find(|t| t.name != name && t.display_name.as_deref() == Some(name))
+            .map_or_else(|| name.to_string(), |t| t.name.clone())
+    }
+
+    /// Path this config was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Tailscale binary to run: `TAILSWITCH_TAILSCALE_BIN`, then `tailscale_path`,
+    /// then `tailscale` from PATH or the platform's install location
+    pub fn tailscale_binary(&self) -> String {
+        std::env::var("TAILSWITCH_TAILSCALE_BIN")
+            .ok()
+            .filter(|bin| !bin.is_empty())
+            .or_else(|| self.tailscale_path.clone())
+            .unwrap_or_else(tailscale::default_binary)
+    }
+
+    /// Timeout for tailscale commands whose output is read, `None` if disabled with 0
+    pub fn command_timeout(&self) -> Option<Duration> {
+        match self.command_timeout_secs {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(runner::DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// How often to refresh status in the background, `None` for manual
+    /// refresh only (0)
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        match self.refresh_interval_secs {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(DEFAULT_REFRESH_INTERVAL),
+        }
+    }
+
+    /// How long to wait for the connection after switching before saying it
+    /// isn't up yet
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout_secs
+            .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_secs)
+    }
+
+    /// How long before the node key expires to warn about it, `None` if
+    /// disabled with 0
+    pub fn key_expiry_warning(&self) -> Option<Duration> {
+        match self
+            .key_expiry_warn_days
+            .unwrap_or(DEFAULT_KEY_EXPIRY_WARN_DAYS)
+        {
+            0 => None,
+            // Saturate rather than overflow on an absurd number of days
+            days => Some(Duration::from_secs(days.saturating_mul(24 * 60 * 60))),
+        }
+    }
+
+    /// Where saved output goes: `output_dir` (which may use `$VAR`s), or the
+    /// current directory
+    pub fn output_dir(&self) -> Result<PathBuf> {
+        match self.output_dir {
+            Some(ref dir) => Ok(PathBuf::from(expand_env(dir, "output_dir")?)),
+            None => std::env::current_dir().context("Failed to get the current directory"),
+        }
+    }
+
+    /// The configured TUI theme, or mono when colors are disabled
+    pub fn theme(&self) -> Theme {
+        Theme::resolve(self.theme.as_deref())
+    }
+
+    /// Whether to show desktop notifications, defaulting to on where a
+    /// notification daemon is usually available
+    pub fn notifications_enabled(&self) -> bool {
+        self.notifications
+            .unwrap_or(cfg!(any(target_os = "linux", target_os = "macos")))
+    }
+}
+
+impl Tailnet {
+    /// Copy with `$VAR` / `${VAR}` references in `login_server`, `auth_key`,
+    /// `auth_key_file` and `flags` replaced by their values. The config itself
+    /// keeps the templates, so secrets never get written back to the file.
+    pub fn expanded(&self) -> Result<Self> {
+        let owner = format!("tailnet '{}'", self.name);
+        let expand = |value: &String| expand_env(value, &owner);
+
+        Ok(Self {
+            login_server: self.login_server.as_ref().map(expand).transpose()?,
+            auth_key: self.auth_key.as_ref().map(expand).transpose()?,
+            auth_key_file: self.auth_key_file.as_ref().map(expand).transpose()?,
+            flags: self
+                .flags
+                .as_ref()
+                .map(|flags| flags.iter().map(expand).collect::<Result<Vec<_>>>())
+                .transpose()?,
+            ..self.clone()
+        })
+    }
+
+    /// `advertise_routes` and `advertise_exit_node` as `tailscale up` flags.
+    /// An empty route list stops advertising routes.
+    pub fn advertise_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(ref routes) = self.advertise_routes {
+            flags.push(format!("--advertise-routes={}", routes.join(",")));
+        }
+        match self.advertise_exit_node {
+            Some(true) => flags.push("--advertise-exit-node".to_string()),
+            Some(false) => flags.push("--advertise-exit-node=false".to_string()),
+            None => {}
+        }
+        flags
+    }
+
+    /// Whether any auth key source is configured
+    pub fn has_auth_key(&self) -> bool {
+        self.auth_key_sources() > 0
+    }
+
+    fn auth_key_sources(&self) -> usize {
+        [
+            self.auth_key.is_some(),
+            self.auth_key_file.is_some(),
+            self.auth_key_command.is_some(),
+        ]
+        .into_iter()
+        .filter(|&set| set)
+        .count()
+    }
+
+    /// The auth key from whichever of `auth_key`, `auth_key_file` or
+    /// `auth_key_command` is set. Files and commands are read on every call
+    /// so rotated keys are picked up.
+    pub fn resolve_auth_key(&self) -> Result<Option<String>> {
+        if self.auth_key_sources() > 1 {
+            anyhow::bail!(
+                "Tailnet '{}' sets more than one of auth_key, auth_key_file and auth_key_command",
+                self.name
+            );
+        }
+
+        let key = if let Some(ref key) = self.auth_key {
+            key.clone()
+        } else if let Some(ref path) = self.auth_key_file {
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read auth_key_file {}", path))?
+        } else if let Some(ref command) = self.auth_key_command {
+            let output = runner::shell_command(command)
+                .output()
+                .with_context(|| format!("Failed to run auth_key_command '{}'", command))?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "auth_key_command '{}' failed: {}",
+                    command,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        } else {
+            return Ok(None);
+        };
+
+        let key = key.trim();
+        if key.is_empty() {
+            anyhow::bail!("Auth key for tailnet '{}' is empty", self.name);
+        }
+        Ok(Some(key.to_string()))
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            tailscale_path: None,
+            notifications: None,
+            default_flags: None,
+            post_switch: None,
+            browser: None,
+            incognito: None,
+            command_timeout_secs: None,
+            theme: None,
+            key_expiry_warn_days: None,
+            connect_timeout_secs: None,
+            refresh_interval_secs: None,
+            output_dir: None,
+            path: PathBuf::new(),
             tailnets: vec![
                 Tailnet {
                     name: "Personal".to_string(),
-                    login_server: None,
-                    auth_key: None,
-                    flags: None,
+                    ..Default::default()
                 },
                 Tailnet {
                     name: "Work".to_string(),
                     login_server: Some("https://login.tailscale.com".to_string()),
-                    auth_key: None,
-                    flags: None,
+                    ..Default::default()
                 },
             ],
         }
     }
 }
+
+/// Expand `$VAR` / `${VAR}`, naming `owner` if a variable isn't set
+fn expand_env(value: &str, owner: &str) -> Result<String> {
+    shellexpand::env(value)
+        .map(|expanded| expanded.into_owned())
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Environment variable {} used by {} is not set",
+                e.var_name,
+                owner
+            )
+        })
+}
+
+/// Combine `default_flags` with a tailnet's own flags. Defaults come first; a
+/// tailnet flag with the same name (`--accept-routes=false` vs
+/// `--accept-routes`) replaces the default, and repeats of a flag are dropped.
+/// A dropped flag takes its separate value (the `foo` of `--hostname foo`) with it.
+fn merge_flags(defaults: &[String], flags: &[String]) -> Vec<String> {
+    let flag_name = |flag: &str| flag.split('=').next().unwrap_or(flag).to_string();
+    let overridden: HashSet<String> = flags
+        .iter()
+        .filter(|flag| flag.starts_with('-'))
+        .map(|flag| flag_name(flag))
+        .collect();
+
+    let mut merged = Vec::new();
+    let mut seen = HashSet::new();
+    for (list, is_default) in [(defaults, true), (flags, false)] {
+        let mut dropping = false;
+        for flag in list {
+            if flag.starts_with('-') {
+                let replaced = is_default && overridden.contains(&flag_name(flag));
+                dropping = replaced || !seen.insert(flag.as_str());
+            }
+            // Anything else is a value, which goes wherever its flag went
+            if !dropping {
+                merged.push(flag.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Check a subnet in CIDR notation as tailscale expects it: an IPv4 or IPv6
+/// network address with no host bits set. The error says what's wrong with it.
+fn parse_cidr(value: &str) -> std::result::Result<(), String> {
+    let Some((address, prefix)) = value.split_once('/') else {
+        return Err("is missing the /prefix length".to_string());
+    };
+    let address: IpAddr = address
+        .parse()
+        .map_err(|_| format!("has an invalid address '{}'", address))?;
+    let (bits, max) = match address {
+        IpAddr::V4(v4) => (u128::from(u32::from(v4)) << 96, 32),
+        IpAddr::V6(v6) => (u128::from(v6), 128),
+    };
+    let prefix: u32 = match prefix.parse() {
+        Ok(prefix) if prefix <= max => prefix,
+        _ => return Err(format!("has an invalid prefix length '/{}'", prefix)),
+    };
+
+    let host_bits = u128::MAX.checked_shr(prefix).unwrap_or(0);
+    if bits & host_bits != 0 {
+        return Err("has host bits set (use the network address)".to_string());
+    }
+    Ok(())
+}
+
+/// Split an `http(s)://host[:port]/...` URL into host and port (defaulting
+/// to the scheme's port). The error says what's wrong with it.
+fn parse_http_url(value: &str) -> std::result::Result<(String, u16), String> {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return Err("is missing the http:// or https:// scheme".to_string());
+    };
+    let default_port = match scheme.to_ascii_lowercase().as_str() {
+        "https" => 443,
+        "http" => 80,
+        _ => return Err(format!("has unsupported scheme '{}'", scheme)),
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // Drop any user:password@ prefix
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+
+    let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+        // IPv6 literal, e.g. [fd7a::1]:8080
+        let (host, after) = bracketed
+            .split_once(']')
+            .ok_or_else(|| "has an unterminated IPv6 address".to_string())?;
+        (host, after.strip_prefix(':'))
+    } else {
+        match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+
+    let valid_host = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':');
+    if host.is_empty() {
+        return Err("has no host".to_string());
+    }
+    // Only bracketed IPv6 literals contain ':' here
+    let is_ipv6 = host.contains(':');
+    let labels_ok = is_ipv6 || !host.trim_end_matches('.').split('.').any(str::is_empty);
+    if !host.chars().all(valid_host) || !labels_ok {
+        return Err(format!("has an invalid host '{}'", host));
+    }
+
+    let port = match port {
+        None => default_port,
+        Some(port) => port
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| format!("has an invalid port '{}'", port))?,
+    };
+
+    Ok((host.to_string(), port))
+}
+
+/// Update `old` to match `new`, only touching keys whose values changed so
+/// comments and formatting elsewhere are kept
+fn merge_table(old: &mut Table, new: &Table) {
+    let removed: Vec<String> = old
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new.contains_key(key))
+        .collect();
+    for key in removed {
+        old.remove(&key);
+    }
+
+    for (key, new_item) in new.iter() {
+        match (old.get_mut(key), new_item) {
+            (Some(Item::Table(old_table)), Item::Table(new_table)) => {
+                merge_table(old_table, new_table)
+            }
+            (Some(Item::ArrayOfTables(old_array)), Item::ArrayOfTables(new_array)) => {
+                merge_array_of_tables(old_array, new_array)
+            }
+            (Some(Item::Value(old_value)), Item::Value(new_value)) => {
+                if normalized(old_value) != normalized(new_value) {
+                    // Keep the surrounding whitespace and trailing comment
+                    let decor = old_value.decor().clone();
+                    *old_value = new_value.clone();
+                    *old_value.decor_mut() = decor;
+                }
+            }
+            _ => {
+                old.insert(key, new_item.clone());
+            }
+        }
+    }
+}
+
+/// Merge `[[tailnets]]`-style arrays, matching entries by `name`. An entry
+/// whose name disappeared at the same position is treated as renamed.
+fn merge_array_of_tables(old: &mut ArrayOfTables, new: &ArrayOfTables) {
+    let name = |table: &Table| table.get("name").and_then(Item::as_str).map(str::to_string);
+
+    let mut previous: Vec<Option<Table>> = old.iter().cloned().map(Some).collect();
+    old.clear();
+
+    for (index, new_table) in new.iter().enumerate() {
+        let new_name = name(new_table);
+        let matching = previous
+            .iter()
+            .position(|table| {
+                table
+                    .as_ref()
+                    .is_some_and(|table| new_name.is_some() && name(table) == new_name)
+            })
+            .or_else(|| {
+                previous
+                    .get(index)
+                    .and_then(Option::as_ref)
+                    .filter(|table| !new.iter().any(|t| name(t) == name(table)))
+                    .map(|_| index)
+            });
+
+        let table = match matching.and_then(|i| previous[i].take()) {
+            Some(mut table) => {
+                merge_table(&mut table, new_table);
+                table
+            }
+            None => {
+                let mut table = new_table.clone();
+                table.set_position(None);
+                table
+            }
+        };
+        old.push(table);
+    }
+}
+
+/// A value's text without decoration, for comparing old and new values
+fn normalized(value: &Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    if let Value::Array(array) = &mut value {
+        array.fmt();
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn config_with(default_flags: &[&str], flags: &[&str]) -> Config {
+        Config {
+            default_flags: Some(strings(default_flags)),
+            tailnets: vec![Tailnet {
+                name: "work".to_string(),
+                flags: Some(strings(flags)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_flags_come_first() {
+        let config = config_with(&["--ssh", "--accept-dns=false"], &["--hostname=laptop"]);
+        assert_eq!(
+            config.resolve_tailnet("work").unwrap().flags,
+            Some(strings(&[
+                "--ssh",
+                "--accept-dns=false",
+                "--hostname=laptop"
+            ]))
+        );
+    }
+
+    #[test]
+    fn tailnet_flags_replace_defaults_with_the_same_name() {
+        let config = config_with(&["--accept-routes", "--ssh"], &["--accept-routes=false"]);
+        assert_eq!(
+            config.resolve_tailnet("work").unwrap().flags,
+            Some(strings(&["--ssh", "--accept-routes=false"]))
+        );
+    }
+
+    #[test]
+    fn repeated_flags_are_dropped() {
+        let config = config_with(&["--ssh", "--ssh"], &["--shields-up", "--shields-up"]);
+        assert_eq!(
+            config.resolve_tailnet("work").unwrap().flags,
+            Some(strings(&["--ssh", "--shields-up"]))
+        );
+    }
+
+    #[test]
+    fn separate_flag_values_are_kept() {
+        assert_eq!(
+            merge_flags(
+                &strings(&["--operator", "alice"]),
+                &strings(&["--hostname", "alice"])
+            ),
+            strings(&["--operator", "alice", "--hostname", "alice"])
+        );
+    }
+
+    #[test]
+    fn replaced_defaults_take_their_separate_value_with_them() {
+        assert_eq!(
+            merge_flags(
+                &strings(&["--hostname", "foo", "--ssh"]),
+                &strings(&["--hostname=bar"])
+            ),
+            strings(&["--ssh", "--hostname=bar"])
+        );
+        assert_eq!(
+            merge_flags(
+                &strings(&["--hostname=foo", "--ssh"]),
+                &strings(&["--hostname", "bar"])
+            ),
+            strings(&["--ssh", "--hostname", "bar"])
+        );
+    }
+
+    #[test]
+    fn repeated_flags_take_their_separate_value_with_them() {
+        assert_eq!(
+            merge_flags(
+                &strings(&["--operator", "alice"]),
+                &strings(&["--operator", "alice", "--ssh"])
+            ),
+            strings(&["--operator", "alice", "--ssh"])
+        );
+    }
+
+    #[test]
+    fn key_expiry_warning_saturates_huge_day_counts() {
+        let warning = |days| {
+            Config {
+                key_expiry_warn_days: Some(days),
+                ..Default::default()
+            }
+            .key_expiry_warning()
+        };
+        assert_eq!(warning(0), None);
+        assert_eq!(warning(2), Some(Duration::from_secs(2 * 86_400)));
+        assert_eq!(warning(u64::MAX), Some(Duration::from_secs(u64::MAX)));
+    }
+
+    #[test]
+    fn unconfigured_tailnets_get_the_defaults() {
+        let config = config_with(&["--ssh"], &[]);
+        assert_eq!(
+            config.resolve_tailnet("elsewhere").unwrap().flags,
+            Some(strings(&["--ssh"]))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Parse an RFC 3339 timestamp as Go writes them, e.g.
+/// "2025-03-01T12:00:00Z" or "2025-03-01T12:00:00.5+01:00"
+pub fn parse_rfc3339(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.split_once(['T', 't'])?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+
+    // Split off the zone: "Z" or "+hh:mm" / "-hh:mm"
+    let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let at = time.rfind(['+', '-'])?;
+        let (hours, minutes) = time[at + 1..].split_once(':')?;
+        let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+        let sign = if time[at..].starts_with('-') { -1 } else { 1 };
+        (&time[..at], sign * offset)
+    };
+    let clock = clock.split('.').next()?;
+    let mut clock = clock.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+
+    let seconds =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+/// `time` in UTC as "20250301T120000Z", for file names
+pub fn compact_utc(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// Both conversions are Howard Hinnant's algorithms, with March as the first
+// month of the year so the leap day falls at the end.
+
+/// Days since the Unix epoch for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// (year, month, day) for a number of days since the Unix epoch
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(text: &str) -> u64 {
+        parse_rfc3339(text)
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn parses_utc_timestamps() {
+        assert_eq!(seconds("1970-01-01T00:00:00Z"), 0);
+        assert_eq!(seconds("2025-03-01T12:00:00Z"), 1740830400);
+        assert_eq!(seconds("2025-03-01t12:00:00z"), 1740830400);
+    }
+
+    #[test]
+    fn ignores_fractional_seconds() {
+        assert_eq!(seconds("2025-03-01T12:00:00.999999999Z"), 1740830400);
+        assert_eq!(seconds("2025-03-01T13:00:00.5+01:00"), 1740830400);
+    }
+
+    #[test]
+    fn applies_zone_offsets() {
+        assert_eq!(seconds("2025-03-01T13:00:00+01:00"), 1740830400);
+        assert_eq!(seconds("2025-03-01T06:30:00-05:30"), 1740830400);
+    }
+
+    #[test]
+    fn handles_leap_days() {
+        assert_eq!(seconds("2024-02-29T00:00:00Z"), 1709164800);
+        assert_eq!(seconds("2024-03-01T00:00:00Z"), 1709164800 + 86400);
+        assert_eq!(seconds("2000-02-29T00:00:00Z"), 951782400);
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        assert!(parse_rfc3339("2025-03-01").is_none());
+        assert!(parse_rfc3339("2025-03-01T12:00:00").is_none());
+        assert!(parse_rfc3339("1969-12-31T23:59:59Z").is_none());
+    }
+
+    #[test]
+    fn formats_compact_utc() {
+        let at = |secs| compact_utc(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "19700101T000000Z");
+        assert_eq!(at(1740830400), "20250301T120000Z");
+        assert_eq!(at(1709164800 + 3661), "20240229T010101Z");
+        assert_eq!(at(951782400), "20000229T000000Z");
+    }
+
+    #[test]
+    fn round_trips_every_day_across_leap_years() {
+        for days in -800..20_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(
+            civil_from_days(days_from_civil(2100, 3, 1) - 1),
+            (2100, 2, 28)
+        );
+    }
+}
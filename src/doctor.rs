@@ -0,0 +1,123 @@
+//! `tailswitch doctor`: check the environment tailswitch depends on and
+//! suggest fixes for what's wrong
+
+use crate::config::Config;
+use crate::exit::Failure;
+use crate::tailscale::{self, TailscaleClient};
+use std::path::Path;
+
+/// Run every check and print the results. Fails when a check that tailswitch
+/// can't work without fails; warnings don't count.
+pub fn run(config_path: Option<&Path>) -> anyhow::Result<()> {
+    let mut failed = false;
+
+    // Config::load would write a default config on first run
+    let config = match Config::load_existing(config_path) {
+        Ok(Some(config)) => {
+            pass(&format!(
+                "Config {} ({} tailnets configured)",
+                config.path().display(),
+                config.tailnets.len()
+            ));
+            config
+        }
+        Ok(None) => {
+            let path = Config::default_path()
+                .map(|path| format!(" at {}", path.display()))
+                .unwrap_or_default();
+            pass(&format!("No config yet{} (defaults will be created)", path));
+            Config::default()
+        }
+        Err(e) => {
+            fail(
+                &format!("Config: {:#}", e),
+                "Fix the file, or move it aside to start from defaults",
+            );
+            failed = true;
+            Config::default()
+        }
+    };
+
+    let binary = config.tailscale_binary();
+    if !TailscaleClient::check_installed(&binary).unwrap_or(false) {
+        fail(
+            &format!("tailscale binary `{}` not found", binary),
+            "Install tailscale (https://tailscale.com/download), or set tailscale_path in the config / TAILSWITCH_TAILSCALE_BIN",
+        );
+        return Err(Failure::NotInstalled.into());
+    }
+    let client = TailscaleClient::new(false, &binary, config.command_timeout());
+    match client.version() {
+        Ok(version) => pass(&format!("tailscale {} ({})", version, binary)),
+        Err(e) => warn(
+            &format!(
+                "tailscale found at {}, but its version is unknown: {:#}",
+                binary, e
+            ),
+            "Check that the binary is a working tailscale CLI",
+        ),
+    }
+
+    if !TailscaleClient::check_daemon_running(&binary) {
+        let hint = match tailscale::daemon_start_command() {
+            Some(command) => format!("Start it with `{}`", command.join(" ")),
+            None => "Start the Tailscale service".to_string(),
+        };
+        fail("tailscaled is not running", &hint);
+        return Err(Failure::DaemonDown.into());
+    }
+    pass("tailscaled is running");
+
+    if TailscaleClient::check_needs_sudo(&binary) {
+        warn(
+            "Changing tailscale settings needs sudo",
+            "Set yourself as operator once with `tailswitch set-operator`",
+        );
+    } else {
+        pass("tailscale can be used without sudo");
+    }
+
+    match client.list_profiles() {
+        Ok(profiles) if !profiles.is_empty() => {
+            pass(&format!("{} tailscale profiles", profiles.len()))
+        }
+        Ok(_) if !config.tailnets.is_empty() => warn(
+            "No tailscale profiles yet",
+            "Select a configured tailnet in tailswitch to log in to it",
+        ),
+        Ok(_) => {
+            fail(
+                "No tailscale profiles and no tailnets configured",
+                &format!(
+                    "Log in with `sudo tailscale login`, or add tailnets to {}",
+                    config.path().display()
+                ),
+            );
+            failed = true;
+        }
+        Err(e) => {
+            fail(
+                &format!("Couldn't list profiles: {:#}", e),
+                "Check that tailscale works on its own with `tailscale switch --list`",
+            );
+            failed = true;
+        }
+    }
+
+    if failed {
+        anyhow::bail!("Some checks failed");
+    }
+    Ok(())
+}
+
+fn pass(message: &str) {
+    println!("✓ {}", message);
+}
+
+fn warn(message: &str, hint: &str) {
+    println!("⚠ {}\n    → {}", message, hint);
+}
+
+fn fail(message: &str, hint: &str) {
+    println!("✗ {}\n    → {}", message, hint);
+}
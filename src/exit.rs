@@ -0,0 +1,58 @@
+//! Exit codes, so scripts wrapping tailswitch can tell failures apart
+
+use std::fmt;
+use std::process::ExitCode;
+
+/// A failure with its own exit code. Tag an error with `.context(Failure::..)`;
+/// untagged errors exit 1, and clap exits 2 for usage errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The config file couldn't be read or is invalid
+    Config,
+    /// The tailscale binary wasn't found
+    NotInstalled,
+    /// tailscaled isn't running or reachable
+    DaemonDown,
+    /// A login was started and the auth URL shown, but it isn't finished yet
+    NeedsAuth,
+    /// Switching to the tailnet (or logging in to it) failed
+    SwitchFailed,
+}
+
+impl Failure {
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Config => 3,
+            Failure::NotInstalled => 4,
+            Failure::DaemonDown => 5,
+            Failure::NeedsAuth => 6,
+            Failure::SwitchFailed => 7,
+        }
+    }
+
+    /// Exit right away, for failures that have already been explained
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code().into())
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Failure::Config => "Failed to load configuration",
+            Failure::NotInstalled => "tailscale is not installed",
+            Failure::DaemonDown => "tailscaled is not running",
+            Failure::NeedsAuth => "Not connected yet: finish logging in in your browser",
+            Failure::SwitchFailed => "Failed to switch tailnet",
+        })
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// The exit code for `error`: its outermost `Failure`, otherwise 1
+pub fn code_for(error: &anyhow::Error) -> ExitCode {
+    error
+        .downcast_ref::<Failure>()
+        .map_or(ExitCode::FAILURE, |failure| ExitCode::from(failure.code()))
+}
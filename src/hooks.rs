@@ -0,0 +1,60 @@
+use crate::runner;
+
+/// Run a `post_switch` command through the shell (`cmd` on Windows) with
+/// `TAILSWITCH_TAILNET` and `TAILSWITCH_IP` set, returning a report with its
+/// output and exit status. The switch itself already succeeded, so failures
+/// are only reported as warnings.
+pub fn run_post_switch(command: &str, tailnet_name: &str, ip: Option<&str>) -> String {
+    let output = runner::shell_command(command)
+        .env("TAILSWITCH_TAILNET", tailnet_name)
+        .env("TAILSWITCH_IP", ip.unwrap_or_default())
+        .output();
+
+    let mut report = format!("Running post_switch hook: {}\n", command);
+    match output {
+        Ok(output) => {
+            for stream in [&output.stdout, &output.stderr] {
+                let text = String::from_utf8_lossy(stream);
+                if !text.trim().is_empty() {
+                    report.push_str(text.trim_end());
+                    report.push('\n');
+                }
+            }
+            if output.status.success() {
+                report.push_str("✓ post_switch hook finished");
+            } else {
+                report.push_str(&format!("⚠ post_switch hook failed ({})", output.status));
+            }
+        }
+        Err(e) => report.push_str(&format!("⚠ Failed to run post_switch hook: {}", e)),
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_output_and_success() {
+        let report = run_post_switch(
+            r#"echo "$TAILSWITCH_TAILNET $TAILSWITCH_IP"; echo oops >&2"#,
+            "work",
+            Some("100.64.0.1"),
+        );
+        assert_eq!(
+            report,
+            "Running post_switch hook: echo \"$TAILSWITCH_TAILNET $TAILSWITCH_IP\"; echo oops >&2\n\
+             work 100.64.0.1\noops\n✓ post_switch hook finished"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_a_failing_hook_as_a_warning() {
+        let report = run_post_switch("echo mounting; exit 3", "work", None);
+        assert!(report.contains("\nmounting\n"));
+        assert!(report.ends_with("⚠ post_switch hook failed (exit status: 3)"));
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where tailscaled listens for LocalAPI requests on Linux and the BSDs
+const SOCKET_PATHS: [&str; 2] = [
+    "/var/run/tailscale/tailscaled.sock",
+    "/run/tailscale/tailscaled.sock",
+];
+
+/// Read-only client for tailscaled's LocalAPI, spoken as plain HTTP over its
+/// unix socket. Much cheaper than spawning the CLI for status and profiles.
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Clone)]
+pub struct LocalApi {
+    socket: PathBuf,
+    timeout: Option<Duration>,
+}
+
+impl LocalApi {
+    /// Find the tailscaled socket. `None` when there isn't one (e.g. macOS
+    /// and Windows, where the CLI is used instead).
+    pub fn detect(timeout: Option<Duration>) -> Option<Self> {
+        if !cfg!(unix) {
+            return None;
+        }
+
+        SOCKET_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+            .map(|socket| Self { socket, timeout })
+    }
+
+    /// GET a LocalAPI endpoint (e.g. `status`) and return the response body.
+    /// Fails on connection or permission errors and non-200 responses.
+    #[cfg(unix)]
+    pub fn get(&self, endpoint: &str) -> Result<String> {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.socket)
+            .with_context(|| format!("Failed to connect to {}", self.socket.display()))?;
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+
+        // HTTP/1.0 so the daemon closes the connection instead of chunking
+        write!(
+            stream,
+            "GET /localapi/v0/{} HTTP/1.0\r\nHost: local-tailscaled.sock\r\n\r\n",
+            endpoint
+        )?;
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .context("Failed to read LocalAPI response")?;
+        let response = String::from_utf8_lossy(&response);
+
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .context("Malformed LocalAPI response")?;
+        let status_line = head.lines().next().unwrap_or_default();
+        let code = status_line.split_whitespace().nth(1).unwrap_or_default();
+
+        if code != "200" {
+            anyhow::bail!(
+                "LocalAPI {} failed: {} {}",
+                endpoint,
+                status_line,
+                body.trim()
+            );
+        }
+
+        Ok(body.to_string())
+    }
+
+    #[cfg(not(unix))]
+    pub fn get(&self, endpoint: &str) -> Result<String> {
+        anyhow::bail!("LocalAPI {} is only available over a unix socket", endpoint)
+    }
+}
@@ -0,0 +1,45 @@
+//! Diagnostic logging, enabled with `-v`/`-vv` or `RUST_LOG`
+
+use anyhow::{Context, Result};
+use log::LevelFilter;
+use std::path::PathBuf;
+
+/// Start logging if asked for: `-v` logs tailswitch's debug messages, `-vv`
+/// adds trace ones, and `RUST_LOG` (env_logger syntax) takes precedence over
+/// both. With `to_file` the log goes to a new owner-only temp file instead of
+/// stderr, whose path is returned so it can be shown on exit.
+pub fn init(verbosity: u8, to_file: bool) -> Result<Option<PathBuf>> {
+    let from_env = std::env::var_os("RUST_LOG").is_some_and(|value| !value.is_empty());
+    if verbosity == 0 && !from_env {
+        return Ok(None);
+    }
+
+    let mut builder = env_logger::Builder::new();
+    if from_env {
+        builder.parse_default_env();
+    } else {
+        let level = if verbosity > 1 {
+            LevelFilter::Trace
+        } else {
+            LevelFilter::Debug
+        };
+        // Only our own messages; the libraries' are rarely useful here
+        builder.filter_module("tailswitch", level);
+    }
+    builder.format_timestamp_millis();
+
+    let mut path = None;
+    if to_file {
+        let (file, file_path) = tempfile::Builder::new()
+            .prefix("tailswitch-")
+            .suffix(".log")
+            .tempfile()
+            .and_then(|file| file.keep().map_err(|e| e.error))
+            .context("Failed to create log file")?;
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+        path = Some(file_path);
+    }
+
+    builder.try_init().context("Failed to start logging")?;
+    Ok(path)
+}
@@ -1,27 +1,132 @@
+mod browser;
+mod cli;
 mod config;
+mod datetime;
+mod doctor;
+mod exit;
+mod hooks;
+mod localapi;
+mod logging;
+mod netcheck;
+mod notification;
+mod options;
+mod runner;
+mod state;
+mod status;
 mod tailscale;
+mod theme;
 mod ui;
 
 use anyhow::{Context, Result};
-use config::Config;
-use tailscale::TailscaleClient;
-use ui::{App, AppAction, UrlDisplayApp};
+use clap::Parser;
+use cli::{Cli, Command, StatusFormat};
+use config::{Config, Tailnet};
+use exit::Failure;
+use netcheck::NetcheckReport;
+use options::TailnetOption;
+use state::State;
+use status::StatusInfo;
+use std::io::{self, IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use tailscale::{PendingLogin, Profile, TailscaleClient};
+use theme::Theme;
+use ui::{App, AppAction, UrlAction, UrlDisplayApp};
+
+/// How long to wait for tailscaled to answer after starting it
+const DAEMON_START_POLLS: u32 = 20;
+const DAEMON_START_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit::code_for(&e)
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    ui::install_panic_hook();
+    let cli = Cli::parse();
+    // The TUI owns the terminal, so it logs to a file instead of stderr
+    let log_path = logging::init(cli.verbose, cli.command.is_none())?;
+    theme::set_no_color(
+        cli.no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+    );
+
+    // Completions don't need tailscale or sudo, only the configured names and aliases
+    if let Some(Command::Completions { shell }) = cli.command {
+        let names = Config::load(cli.config.as_deref())
+            .map(|config| {
+                config
+                    .tailnets
+                    .into_iter()
+                    .flat_map(|t| std::iter::once(t.name).chain(t.display_name))
+                    .collect()
+            })
+            .unwrap_or_default();
+        cli::print_completions(shell, names);
+        return Ok(());
+    }
+
+    // Doctor reports a broken config or install itself rather than stopping at it
+    if let Some(Command::Doctor) = cli.command {
+        return doctor::run(cli.config.as_deref());
+    }
+
+    // Load config (optional - for adding new tailnets)
+    let config = Config::load(cli.config.as_deref()).context(Failure::Config)?;
+    if cli.check_servers {
+        config.check_login_servers()?;
+    }
+    let tailscale_bin = config.tailscale_binary();
+    // Set up once; the client used for the rest of the run is derived from it
+    // once it's known whether tailscale needs sudo
+    let base_client = TailscaleClient::new(false, &tailscale_bin, config.command_timeout());
+
+    // Status bars poll this, so it must never prompt (daemon start, sudo) or
+    // open the TUI. Reading status doesn't need root.
+    if let Some(Command::Status { format }) = cli.command {
+        return print_status(base_client.status_json(), format);
+    }
+    if let Some(Command::Current) = cli.command {
+        print_current(base_client.status_json().ok());
+    }
 
-fn main() -> Result<()> {
     // Check if tailscale is installed
-    if !TailscaleClient::check_installed()? {
-        eprintln!("Error: tailscale is not installed or not in PATH");
+    if !TailscaleClient::check_installed(&tailscale_bin)? {
+        eprintln!("Error: {} is not installed or not in PATH", tailscale_bin);
         eprintln!("Please install tailscale first: https://tailscale.com/download");
-        std::process::exit(1);
+        eprintln!("Or set tailscale_path in your config / TAILSWITCH_TAILSCALE_BIN");
+        eprintln!("Run `tailswitch doctor` to check the rest of your setup");
+        Failure::NotInstalled.exit();
+    }
+
+    // Everything below talks to tailscaled, so say so plainly if it's down
+    let daemon_running = TailscaleClient::check_daemon_running(&tailscale_bin)
+        || offer_to_start_daemon(&tailscale_bin, cli.dry_run)?;
+    // The TUI can still show the config offline; subcommands need tailscale
+    if !daemon_running && cli.command.is_some() {
+        Failure::DaemonDown.exit();
+    }
+
+    if let Some(Command::SetOperator) = cli.command {
+        return set_operator(&base_client, &tailscale_bin, cli.dry_run);
     }
 
     // Check if we need sudo
-    let needs_sudo = TailscaleClient::check_needs_sudo();
+    let mut needs_sudo = daemon_running && TailscaleClient::check_needs_sudo(&tailscale_bin);
+    if needs_sudo && offer_operator_setup(&config, &base_client, &tailscale_bin, cli.dry_run)? {
+        needs_sudo = false;
+    }
     if needs_sudo {
         eprintln!("Note: tailscale requires elevated permissions.");
         eprintln!("You can either:");
         eprintln!("  1. Run with sudo: sudo tailswitch");
-        eprintln!("  2. Set yourself as operator once: sudo tailscale set --operator=$USER");
+        eprintln!("  2. Set yourself as operator once: tailswitch set-operator");
         eprintln!();
         eprintln!("Attempting to use sudo for tailscale commands...");
         eprintln!();
@@ -37,442 +142,389 @@ fn main() -> Result<()> {
         eprintln!();
     }
 
-    // Load config (optional - for adding new tailnets)
-    let config = Config::load().context("Failed to load configuration")?;
+    // One client for the whole run, shared by the subcommands and TUI actions
+    let client = base_client
+        .clone_with_sudo(needs_sudo)
+        .with_dry_run(cli.dry_run);
 
-    // Get existing tailscale profiles
-    let client = TailscaleClient::new(needs_sudo);
-    let profiles = client.list_profiles().unwrap_or_default();
+    let result = match cli.command {
+        Some(Command::Switch { name }) => {
+            // `switch -` goes back to the previous tailnet, like `cd -`
+            let name = if name == "-" {
+                State::load(&config)
+                    .previous_tailnet
+                    .context("No previous tailnet to switch back to")?
+            } else {
+                config.resolve_alias(&name)
+            };
+            let tailnet = Tailnet {
+                name,
+                ..Default::default()
+            };
+            switch_tailnet(&client, &config, &tailnet, false)
+                .context(Failure::SwitchFailed)
+                .and_then(require_connected)
+        }
+        Some(Command::List { json }) => print_list(&options::load_options(&client, &config), json),
+        Some(Command::Up { name }) => {
+            let name = match name {
+                Some(name) => config.resolve_alias(&name),
+                None => client
+                    .status_json()
+                    .ok()
+                    .filter(|status| !status.is_logged_out())
+                    .and_then(|status| status.tailnet_name)
+                    .context("Not connected to a tailnet; pass a tailnet name")?,
+            };
+            let tailnet = config.resolve_tailnet(&name)?;
+            println!("{}", tailscale_up(&client, &tailnet)?);
+            Ok(())
+        }
+        Some(Command::Logout) => {
+            println!("{}", logout(&client)?);
+            Ok(())
+        }
+        Some(Command::Import) => import_profiles(&client, config),
+        Some(Command::Rename { profile, name }) => {
+            let tailnet = Tailnet {
+                name: config.resolve_alias(&profile),
+                ..Default::default()
+            };
+            let profiles = client.list_profiles()?;
+            let profile = find_profile(&profiles, &tailnet)?
+                .with_context(|| format!("No tailscale profile named '{}'", tailnet.name))?;
+            rename_profile(&client, &profile.id, &name)?;
+            println!("✓ Renamed profile {} to '{}'", profile.id, name.trim());
+            Ok(())
+        }
+        Some(
+            Command::Completions { .. }
+            | Command::Status { .. }
+            | Command::Current
+            | Command::SetOperator
+            | Command::Doctor,
+        ) => {
+            unreachable!("handled before tailscale checks")
+        }
+        None => run_tui(&client, config, !daemon_running),
+    };
 
-    if profiles.is_empty() && config.tailnets.is_empty() {
-        eprintln!("No tailscale profiles found and no tailnets configured!");
-        eprintln!("Please either:");
-        eprintln!("  1. Login to tailscale first: sudo tailscale login");
-        eprintln!(
-            "  2. Or configure tailnets in: {}",
-            Config::get_config_path_string()
-                .unwrap_or_else(|_| "~/.config/tailswitch/config.toml".to_string())
-        );
-        std::process::exit(1);
+    if client.is_dry_run() {
+        print_dry_run(&client.take_dry_run_commands());
+    }
+    if let Some(path) = log_path {
+        eprintln!("Log written to {}", path.display());
+    }
+    result
+}
+
+/// Report what `--dry-run` skipped
+fn print_dry_run(commands: &[String]) {
+    if commands.is_empty() {
+        println!("Dry run: no tailscale commands would be run");
+    } else {
+        println!("Dry run: would run");
+        for command in commands {
+            println!("  {}", command);
+        }
     }
+}
 
-    // Get current status to see which profile is active
-    let current_status = client.status().unwrap_or_default();
-    let is_logged_in = !current_status.contains("Logged out");
+/// What `--dry-run` skipped, for the TUI output view
+fn dry_run_report(commands: &[String]) -> String {
+    format!(
+        "Dry run - nothing was changed. Would run:\n\n{}",
+        commands.join("\n")
+    )
+}
 
-    // Parse the active tailnet from switch --list (has * at the end of account)
-    let active_tailnet = if is_logged_in {
-        profiles
-            .iter()
-            .find(|(_, account)| account.ends_with('*'))
-            .map(|(name, _)| name.clone())
+fn run_tui(client: &TailscaleClient, config: Config, daemon_down: bool) -> Result<()> {
+    // Raw mode and the alternate screen need a real, capable terminal
+    let dumb_terminal = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    let use_tui = io::stdout().is_terminal() && !dumb_terminal;
+
+    let load = || {
+        (
+            options::load_options(client, &config),
+            client.prefs().ok(),
+            client.status_json().ok(),
+            client.version().ok(),
+        )
+    };
+    let (all_options, prefs, status, version) = if daemon_down {
+        (options::config_options(&config), None, None, None)
+    } else if use_tui {
+        ui::with_spinner(config.theme(), "Loading tailscale profiles…", load)?
     } else {
-        None
+        load()
     };
+    // Neither profiles nor status (daemon down, no permission): config only
+    let offline = daemon_down || (status.is_none() && client.list_profiles().is_err());
 
-    // Build list of options: existing profiles + config entries
-    let mut all_options = Vec::new();
-
-    // Add existing profiles first
-    for (tailnet, account) in &profiles {
-        let is_active = active_tailnet
-            .as_ref()
-            .map(|t| t == tailnet)
-            .unwrap_or(false);
-        // Remove * from account name for display
-        let clean_account = account.trim_end_matches('*').to_string();
-        all_options.push((tailnet.clone(), Some(clean_account), true, is_active)); // (name, account, is_profile, is_active)
+    if all_options.is_empty() && offline {
+        eprintln!(
+            "tailscale is unreachable and no tailnets are configured, so there's nothing to show."
+        );
+        Failure::DaemonDown.exit();
+    }
+    if all_options.is_empty() {
+        eprintln!("No tailscale profiles found and no tailnets configured!");
+        eprintln!("Please either:");
+        eprintln!("  1. Login to tailscale first: sudo tailscale login");
+        eprintln!("  2. Or configure tailnets in: {}", config.path().display());
+        std::process::exit(1);
     }
 
-    // Add config entries that don't already exist as profiles
-    for tailnet in &config.tailnets {
-        if !profiles.iter().any(|(name, _)| name == &tailnet.name) {
-            all_options.push((tailnet.name.clone(), None, false, false)); // (name, no account, not a profile, not active)
+    if !use_tui {
+        if io::stdin().is_terminal() {
+            return run_plain_menu(client, &config, &all_options);
         }
+        return print_list(&all_options, false);
     }
 
     // Run the TUI with all options in a loop
-    let mut app = App::new_with_options(all_options, config.clone());
+    // The app owns the config from here on since it can be edited in the TUI
+    let mut app = App::new_with_options(all_options, config);
+    app.set_prefs(prefs);
+    app.set_status(status);
+    app.set_version(version);
+    app.set_offline(offline);
+    if let Some(interval) = app.config().refresh_interval() {
+        // Status is readable without root, and a sudo prompt would garble the TUI
+        app.watch_status(spawn_status_watcher(
+            client.clone_with_sudo(false),
+            interval,
+        ));
+    }
+    let state = State::load(app.config());
+    app.set_previous_tailnet(state.previous_tailnet);
+
+    // Start on the tailnet highlighted last time, or the active one if it's gone
+    let restored = state
+        .last_selected
+        .is_some_and(|name| app.select_tailnet(&name));
+    if !restored && let Some(active) = app.get_active_profile_id() {
+        app.select_tailnet(&active);
+    }
 
     loop {
         let action = app.run().context("Failed to run TUI")?;
+        remember_selection(app.config(), app.get_selected_key());
 
         // Handle the action
         let should_exit = match action {
             Some(AppAction::SelectTailnet(tailnet)) => {
-                println!("Switching to tailnet: {}", tailnet.name);
-
-                let client = TailscaleClient::new(needs_sudo);
-
-                // Check if this profile already exists
-                println!("Checking existing profiles...");
-                let profiles = client.list_profiles().unwrap_or_default();
-
-                let profile_exists = profiles.iter().any(|(name, _)| name == &tailnet.name);
-
-                if profile_exists {
-                    // Profile exists - use fast switching
-                    println!("Found existing profile for '{}'", tailnet.name);
-                    println!("Switching...");
-
-                    match client.switch_to(&tailnet.name) {
-                        Ok(()) => {
-                            println!("✓ Successfully switched to {}!", tailnet.name);
-
-                            // Check if we're logged in after switching
-                            let is_logged_out = client.is_logged_out().unwrap_or(false);
-
-                            if is_logged_out {
-                                println!("\n⚠ Profile is logged out. Starting authentication...");
-
-                                // Look up config for this tailnet to get flags
-                                let tailnet_with_config = if let Some(config_entry) =
-                                    config.tailnets.iter().find(|t| t.name == tailnet.name)
-                                {
-                                    // Use config entry which has flags
-                                    config_entry.clone()
-                                } else {
-                                    // No config entry, use the selected tailnet
-                                    tailnet.clone()
-                                };
-
-                                // Re-authenticate with proper flags
-                                println!("Connecting to {}...", tailnet_with_config.name);
-                                println!("Starting authentication process...");
-
-                                match client
-                                    .login_and_get_url(&tailnet_with_config)
-                                    .context("Failed to start tailscale connection")?
-                                {
-                                    Some(url) => {
-                                        // We got an auth URL - show it in a TUI
-                                        println!(
-                                            "Authentication URL received. Opening URL display..."
-                                        );
-
-                                        let mut url_app = UrlDisplayApp::new(
-                                            url.clone(),
-                                            tailnet_with_config.name.clone(),
-                                        );
-                                        let should_open_browser =
-                                            url_app.run().context("Failed to run URL display")?;
-
-                                        if should_open_browser {
-                                            println!("Opening browser...");
-                                            let script_content = format!(
-                                                r#"#!/bin/sh
-export DISPLAY="${{DISPLAY:-:0}}"
-export WAYLAND_DISPLAY="${{WAYLAND_DISPLAY:-wayland-0}}"
-export XDG_RUNTIME_DIR="${{XDG_RUNTIME_DIR:-/run/user/$(id -u)}}"
-export DBUS_SESSION_BUS_ADDRESS="${{DBUS_SESSION_BUS_ADDRESS:-unix:path=$XDG_RUNTIME_DIR/bus}}"
-exec chromium '{}' >/dev/null 2>&1 &
-"#,
-                                                url.replace("'", "'\\''")
-                                            );
-
-                                            let script_path = "/tmp/tailswitch-open-browser.sh";
-                                            if let Err(e) =
-                                                std::fs::write(script_path, script_content)
-                                            {
-                                                eprintln!(
-                                                    "✗ Failed to create browser script: {}",
-                                                    e
-                                                );
-                                                eprintln!(
-                                                    "\nPlease manually open this URL in your browser:"
-                                                );
-                                                eprintln!("{}", url);
-                                            } else {
-                                                let _ = std::process::Command::new("chmod")
-                                                    .arg("+x")
-                                                    .arg(script_path)
-                                                    .status();
-
-                                                let result = std::process::Command::new("setsid")
-                                                    .arg("-f")
-                                                    .arg(script_path)
-                                                    .spawn();
-
-                                                match result {
-                                                    Ok(_) => {
-                                                        std::thread::sleep(
-                                                            std::time::Duration::from_millis(1000),
-                                                        );
-                                                        println!("✓ Browser launch initiated!");
-                                                        println!(
-                                                            "✓ Please complete authentication in your browser."
-                                                        );
-                                                        println!(
-                                                            "✓ Select the '{}' tailnet when prompted.",
-                                                            tailnet_with_config.name
-                                                        );
-                                                        println!(
-                                                            "\nTailscale is running in the background."
-                                                        );
-                                                        println!(
-                                                            "Run 'tailscale status' in a few moments to verify connection."
-                                                        );
-                                                        println!(
-                                                            "\nIf browser didn't open, manually open this URL:"
-                                                        );
-                                                        println!("{}", url);
-                                                    }
-                                                    Err(e) => {
-                                                        eprintln!(
-                                                            "✗ Failed to launch browser: {}",
-                                                            e
-                                                        );
-                                                        eprintln!(
-                                                            "\nPlease manually open this URL in your browser:"
-                                                        );
-                                                        eprintln!("{}", url);
-                                                    }
-                                                }
-                                            }
-                                        } else {
-                                            println!("Exited without opening browser.");
-                                            println!(
-                                                "You can manually open this URL to complete authentication:"
-                                            );
-                                            println!("{}", url);
-                                            println!(
-                                                "\nTailscale is still running in the background waiting for authentication."
-                                            );
-                                        }
-                                    }
-                                    None => {
-                                        println!(
-                                            "Successfully connected to {}!",
-                                            tailnet_with_config.name
-                                        );
-                                        if let Ok(status) = client.status() {
-                                            println!("\nCurrent status:");
-                                            println!("{}", status);
-                                        }
-                                    }
-                                }
-                                return Ok(());
-                            } else {
-                                // Successfully switched and logged in
-                                if let Ok(status) = client.status() {
-                                    println!("\nCurrent status:");
-                                    println!("{}", status);
-                                }
-                                return Ok(());
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("✗ Switch failed: {}", e);
-                            eprintln!("Will try logging in instead...");
-                        }
-                    }
+                let switched = switch_tailnet(client, app.config(), &tailnet, true)
+                    .context(Failure::SwitchFailed)?;
+                if let Some(report) = switched.hook_report {
+                    // Stay to show what the post_switch hook did
+                    app.set_status(client.status_json().ok());
+                    let output = format!("✓ Switched to {}\n\n{}", tailnet.name, report);
+                    app.show_output(format!("Switch - {}", tailnet.name), output);
+                    false
                 } else {
-                    println!(
-                        "No existing profile for '{}'. Will log in to add it...",
-                        tailnet.name
-                    );
-                }
-
-                // If we get here, need to login (either profile doesn't exist or switch failed)
-                // NOTE: We do NOT logout first! Just run tailscale login/up
-                // This adds a new profile without disturbing existing ones
-                println!("Connecting to {}...", tailnet.name);
-                println!("Starting authentication process...");
-
-                match client
-                    .login_and_get_url(&tailnet)
-                    .context("Failed to start tailscale connection")?
-                {
-                    Some(url) => {
-                        // We got an auth URL - show it in a TUI
-                        println!("Authentication URL received. Opening URL display...");
-
-                        // Log the URL to a file for debugging
-                        let debug_log = format!(
-                            "/tmp/tailswitch-debug-{}.txt",
-                            std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs()
-                        );
-                        let _ = std::fs::write(
-                            &debug_log,
-                            format!("Captured URL: {}\nTailnet: {}\n", url, tailnet.name),
-                        );
-                        println!("Debug info written to: {}", debug_log);
-
-                        let mut url_app = UrlDisplayApp::new(url.clone(), tailnet.name.clone());
-                        let should_open_browser =
-                            url_app.run().context("Failed to run URL display")?;
-
-                        if should_open_browser {
-                            // User pressed Enter - open the browser
-                            println!("Opening browser...");
-
-                            // Create a temporary script with all necessary environment variables
-                            let script_content = format!(
-                                r#"#!/bin/sh
-export DISPLAY="${{DISPLAY:-:0}}"
-export WAYLAND_DISPLAY="${{WAYLAND_DISPLAY:-wayland-0}}"
-export XDG_RUNTIME_DIR="${{XDG_RUNTIME_DIR:-/run/user/$(id -u)}}"
-export DBUS_SESSION_BUS_ADDRESS="${{DBUS_SESSION_BUS_ADDRESS:-unix:path=$XDG_RUNTIME_DIR/bus}}"
-exec chromium '{}' >/dev/null 2>&1 &
-"#,
-                                url.replace("'", "'\\''")
-                            );
-
-                            let script_path = "/tmp/tailswitch-open-browser.sh";
-                            if let Err(e) = std::fs::write(script_path, script_content) {
-                                eprintln!("✗ Failed to create browser script: {}", e);
-                                eprintln!("\nPlease manually open this URL in your browser:");
-                                eprintln!("{}", url);
-                            } else {
-                                // Make it executable
-                                let _ = std::process::Command::new("chmod")
-                                    .arg("+x")
-                                    .arg(script_path)
-                                    .status();
-
-                                // Run with setsid for complete detachment
-                                let result = std::process::Command::new("setsid")
-                                    .arg("-f")
-                                    .arg(script_path)
-                                    .spawn();
-
-                                match result {
-                                    Ok(_) => {
-                                        std::thread::sleep(std::time::Duration::from_millis(1000));
-                                        println!("✓ Browser launch initiated!");
-                                        println!(
-                                            "✓ Please complete authentication in your browser."
-                                        );
-                                        println!(
-                                            "✓ Select the '{}' tailnet when prompted.",
-                                            tailnet.name
-                                        );
-                                        println!("\nTailscale is running in the background.");
-                                        println!(
-                                            "Run 'tailscale status' in a few moments to verify connection."
-                                        );
-                                        println!(
-                                            "\nIf browser didn't open, manually open this URL:"
-                                        );
-                                        println!("{}", url);
-                                    }
-                                    Err(e) => {
-                                        eprintln!("✗ Failed to launch browser: {}", e);
-                                        eprintln!(
-                                            "\nPlease manually open this URL in your browser:"
-                                        );
-                                        eprintln!("{}", url);
-                                    }
-                                }
-                            }
-                        } else {
-                            // User pressed 'q' - exit without opening browser
-                            println!("Exited without opening browser.");
-                            println!("You can manually open this URL to complete authentication:");
-                            println!("{}", url);
-                            println!(
-                                "\nTailscale is still running in the background waiting for authentication."
-                            );
-                        }
-                    }
-                    None => {
-                        // No URL needed (auth key was used) - connection completed
-                        println!("Successfully connected to {}!", tailnet.name);
-
-                        // Show status
-                        if let Ok(status) = client.status() {
-                            println!("\nCurrent status:");
-                            println!("{}", status);
-                        }
-                    }
+                    // A dry run changed nothing, so stay to show what would have run
+                    !client.is_dry_run()
                 }
-                true // Exit after switching
             }
-            Some(AppAction::RunTailscaleUp) => {
-                // Get the currently selected tailnet name
-                let tailnet_name = match app
-                    .get_selected_tailnet_name()
-                    .or_else(|| app.get_active_tailnet_name())
-                {
-                    Some(name) => name,
-                    None => {
-                        app.show_output(
-                            "Error".to_string(),
-                            "No tailnet selected or active".to_string(),
-                        );
-                        continue;
-                    }
+            Some(AppAction::RunTailscaleUp(tailnet)) => {
+                let output = match tailscale_up(client, &tailnet) {
+                    Ok(result) => result,
+                    Err(e) => format!("✗ {:#}", e),
                 };
 
-                // Look up config for this tailnet to get flags
-                let tailnet_config = config
-                    .tailnets
-                    .iter()
-                    .find(|t| t.name == tailnet_name)
-                    .cloned()
-                    .unwrap_or_else(|| config::Tailnet {
-                        name: tailnet_name.clone(),
-                        login_server: None,
-                        auth_key: None,
-                        flags: None,
-                    });
-
-                let client = TailscaleClient::new(needs_sudo);
-
-                let output = match client.run_up(&tailnet_config) {
-                    Ok(()) => {
-                        let mut result = format!(
-                            "✓ Successfully updated connection settings for '{}'!\n",
-                            tailnet_name
+                app.set_status(client.status_json().ok());
+                app.show_output(format!("Tailscale Up - {}", tailnet.name), output);
+                false // Don't exit, show output
+            }
+            Some(AppAction::Reauthenticate(tailnet)) => {
+                let theme = app.config().theme();
+                let output = match authenticate(
+                    client,
+                    &tailnet,
+                    theme,
+                    true,
+                    TailscaleClient::force_reauth_and_get_url,
+                ) {
+                    Ok(switched) => {
+                        let mut output = format!(
+                            "✓ Re-authentication requested for {}\n\n{}",
+                            tailnet.name,
+                            client.status().unwrap_or_default()
                         );
-                        if let Some(ref flags) = tailnet_config.flags {
-                            result.push_str(&format!("\nApplied flags: {}\n", flags.join(" ")));
-                        }
-
-                        // Show status
-                        if let Ok(status) = client.status() {
-                            result.push('\n');
-                            result.push_str(&status);
+                        if let Some(report) = switched.hook_report {
+                            output = format!("{}\n\n{}", output.trim_end(), report);
                         }
-                        result
-                    }
-                    Err(e) => {
-                        format!("✗ Failed to run tailscale up: {}", e)
+                        output
                     }
+                    Err(e) => format!("✗ {:#}", e),
                 };
 
-                app.show_output(format!("Tailscale Up - {}", tailnet_name), output);
+                app.set_status(client.status_json().ok());
+                app.show_output(format!("Re-authenticate - {}", tailnet.name), output);
                 false // Don't exit, show output
             }
             Some(AppAction::ShowStatus) => {
-                let client = TailscaleClient::new(needs_sudo);
                 let output = match client.status() {
                     Ok(status) => status,
                     Err(e) => format!("✗ Failed to get status: {}", e),
                 };
 
-                app.show_output("Tailscale Status".to_string(), output);
+                // Fall back to the raw text if the JSON can't be read
+                match client.status_json() {
+                    Ok(status) => {
+                        app.show_peers(&status, output);
+                        app.set_status(Some(status));
+                    }
+                    Err(_) => app.show_output("Tailscale Status".to_string(), output),
+                }
                 false // Don't exit, show output
             }
+            Some(AppAction::Ping(peer)) => {
+                let target = peer.ips.first().unwrap_or(&peer.hostname);
+                match client.ping(target) {
+                    Ok(ping) => app.show_ping(&peer, ping),
+                    Err(e) => {
+                        app.show_output(format!("Ping - {}", peer.hostname), format!("✗ {:#}", e))
+                    }
+                }
+                false // Stay to watch the replies
+            }
+            Some(AppAction::RefreshProfiles) => {
+                client.invalidate_profiles();
+                app.set_options(options::load_options(client, app.config()));
+                app.set_prefs(client.prefs().ok());
+                let status = client.status_json().ok();
+                let offline = status.is_none() && client.list_profiles().is_err();
+                app.set_status(status);
+                app.set_offline(offline);
+                app.set_status_message(if offline {
+                    "✗ tailscale is still unreachable".to_string()
+                } else {
+                    "✓ Refreshed profiles".to_string()
+                });
+                false // Stay in the list
+            }
             Some(AppAction::Logout) => {
-                let client = TailscaleClient::new(needs_sudo);
-
-                let output = match client.logout() {
-                    Ok(()) => "✓ Successfully logged out!".to_string(),
-                    Err(e) => format!("✗ Failed to logout: {}", e),
+                let output = match logout(client) {
+                    Ok(result) => result,
+                    Err(e) => format!("✗ {:#}", e),
                 };
 
+                app.set_options(options::load_options(client, app.config()));
+                app.set_status(client.status_json().ok());
                 app.show_output("Logout".to_string(), output);
                 false // Don't exit, show output
             }
+            Some(AppAction::DeleteProfile(option)) => {
+                let name = option.name;
+                let id = option.profile_id.unwrap_or_else(|| name.clone());
+                let active = app.get_active_profile_id();
+
+                let output = match client.remove_profile(&id, active.as_deref()) {
+                    Ok(()) => format!("✓ Removed profile '{}'", name),
+                    Err(e) => format!("✗ Failed to remove profile '{}': {}", name, e),
+                };
+
+                // Refresh so the removed row disappears
+                app.set_options(options::load_options(client, app.config()));
+                app.set_status(client.status_json().ok());
+                app.show_output("Delete Profile".to_string(), output);
+                false // Don't exit, show output
+            }
+            Some(AppAction::RenameProfile(option, name)) => {
+                let id = option.profile_id.unwrap_or_else(|| option.name.clone());
+                let output = match rename_profile(client, &id, &name) {
+                    Ok(()) => format!("✓ Renamed profile {} to '{}'", id, name),
+                    Err(e) => format!("✗ Failed to rename profile {}: {:#}", id, e),
+                };
+
+                // Refresh so the row shows its new name
+                app.set_options(options::load_options(client, app.config()));
+                app.set_status(client.status_json().ok());
+                app.show_output("Rename Profile".to_string(), output);
+                false // Don't exit, show output
+            }
+            Some(AppAction::Netcheck) => {
+                let theme = app.config().theme();
+                let output = match ui::with_spinner(theme, "Running tailscale netcheck…", || {
+                    client.netcheck()
+                })? {
+                    // Fall back to the raw report if its format isn't recognized
+                    Ok(report) => NetcheckReport::parse(&report)
+                        .map(|parsed| parsed.summary())
+                        .unwrap_or(report),
+                    Err(e) => format!("✗ {:#}", e),
+                };
+
+                app.show_output("Netcheck".to_string(), output);
+                false // Don't exit, show output
+            }
+            Some(AppAction::PickExitNode) => {
+                match client.exit_node_list() {
+                    Ok(nodes) => {
+                        let current = client.status_json().ok().and_then(|s| s.exit_node);
+                        app.show_exit_nodes(nodes, current);
+                    }
+                    Err(e) => app.show_output(
+                        "Exit Node".to_string(),
+                        format!("✗ Failed to list exit nodes: {}", e),
+                    ),
+                }
+                false // Don't exit, show picker
+            }
+            Some(AppAction::SetExitNode(node)) => {
+                let output = match client.set_exit_node(node.as_deref()) {
+                    Ok(()) => match node {
+                        Some(ref node) => format!("✓ Now using exit node {}", node),
+                        None => "✓ Exit node disabled".to_string(),
+                    },
+                    Err(e) => format!("✗ Failed to set exit node: {}", e),
+                };
+
+                app.show_output("Exit Node".to_string(), output);
+                false // Don't exit, show output
+            }
+            Some(AppAction::ToggleAcceptRoutes) | Some(AppAction::ToggleAcceptDns) => {
+                let routes = matches!(action, Some(AppAction::ToggleAcceptRoutes));
+                let name = if routes {
+                    "accept-routes"
+                } else {
+                    "accept-dns"
+                };
+
+                // Read the current value fresh so we never toggle the wrong way
+                let output = match client.prefs() {
+                    Ok(prefs) => {
+                        let enable = !if routes {
+                            prefs.accept_routes
+                        } else {
+                            prefs.accept_dns
+                        };
+                        match client.set(&format!("--{}={}", name, enable)) {
+                            Ok(()) => {
+                                format!("✓ {} is now {}", name, if enable { "on" } else { "off" })
+                            }
+                            Err(e) => format!("✗ Failed to set {}: {}", name, e),
+                        }
+                    }
+                    Err(e) => format!("✗ Failed to read current {} setting: {}", name, e),
+                };
+
+                app.set_prefs(client.prefs().ok());
+                app.show_output(format!("Toggle {}", name), output);
+                false // Don't exit, show output
+            }
             Some(AppAction::Quit) | None => {
                 true // Exit
             }
         };
 
+        let planned = client.take_dry_run_commands();
+        if !planned.is_empty() {
+            app.show_output("Dry Run".to_string(), dry_run_report(&planned));
+        }
+
         if should_exit {
             break;
         }
@@ -480,3 +532,783 @@ exec chromium '{}' >/dev/null 2>&1 &
 
     Ok(())
 }
+
+/// Re-fetch `tailscale status --json` every `interval` in the background so the
+/// TUI stays current without blocking on input. Stops once the receiver is dropped.
+fn spawn_status_watcher(client: TailscaleClient, interval: Duration) -> Receiver<StatusInfo> {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(interval);
+            let Ok(status) = client.status_json() else {
+                continue;
+            };
+            if sender.send(status).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Explain that tailscaled is down and, only if the user agrees, start it.
+/// Returns whether it's running now.
+fn offer_to_start_daemon(tailscale_bin: &str, dry_run: bool) -> Result<bool> {
+    eprintln!("Error: tailscaled (the Tailscale daemon) isn't running.");
+
+    let Some(command) = tailscale::daemon_start_command() else {
+        eprintln!("Start it and try again.");
+        return Ok(false);
+    };
+    let shown = command.join(" ");
+
+    if dry_run || !io::stdin().is_terminal() {
+        eprintln!("Start it with `{}` and try again.", shown);
+        return Ok(false);
+    }
+
+    eprint!("Start it now with `{}`? [y/N] ", shown);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("Failed to read answer")?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        return Ok(false);
+    }
+
+    let status = std::process::Command::new(command[0])
+        .args(&command[1..])
+        .status()
+        .with_context(|| format!("Failed to run {}", shown))?;
+    if !status.success() {
+        anyhow::bail!("{} failed ({})", shown, status);
+    }
+
+    // The socket takes a moment to appear after the service starts
+    for _ in 0..DAEMON_START_POLLS {
+        if TailscaleClient::check_daemon_running(tailscale_bin) {
+            eprintln!("✓ tailscaled is running");
+            return Ok(true);
+        }
+        std::thread::sleep(DAEMON_START_POLL_INTERVAL);
+    }
+    anyhow::bail!("tailscaled still isn't responding after {}", shown)
+}
+
+/// Ask once whether to make the current user tailscale's operator instead of
+/// using sudo every run. Returns whether that worked, so sudo isn't needed;
+/// a "no" is remembered so the question doesn't come back.
+fn offer_operator_setup(
+    config: &Config,
+    client: &TailscaleClient,
+    tailscale_bin: &str,
+    dry_run: bool,
+) -> Result<bool> {
+    let mut state = State::load(config);
+    if dry_run || state.declined_operator || !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    eprintln!("tailscale needs sudo for your user.");
+    eprint!("Set yourself as operator now, so tailswitch won't need sudo again? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("Failed to read answer")?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        state.declined_operator = true;
+        if let Err(e) = state.save(config) {
+            eprintln!("Warning: {:#}", e);
+        }
+        eprintln!();
+        return Ok(false);
+    }
+
+    match set_operator(client, tailscale_bin, false) {
+        Ok(()) => {
+            eprintln!();
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            eprintln!();
+            Ok(false)
+        }
+    }
+}
+
+/// `tailswitch set-operator`: run `sudo tailscale set --operator=$USER`, so
+/// tailscale takes this user's commands without sudo from then on
+fn set_operator(client: &TailscaleClient, tailscale_bin: &str, dry_run: bool) -> Result<()> {
+    if !TailscaleClient::check_needs_sudo(tailscale_bin) {
+        eprintln!("✓ tailscale already works without sudo");
+        return Ok(());
+    }
+    let user = std::env::var("USER")
+        .ok()
+        .filter(|user| !user.is_empty())
+        .context("$USER is not set, so there's no user to make operator")?;
+
+    let client = client.clone_with_sudo(true).with_dry_run(dry_run);
+    client.set(&format!("--operator={}", user))?;
+    if client.is_dry_run() {
+        print_dry_run(&client.take_dry_run_commands());
+        return Ok(());
+    }
+
+    if TailscaleClient::check_needs_sudo(tailscale_bin) {
+        anyhow::bail!(
+            "Set {} as operator, but tailscale still asks for sudo",
+            user
+        );
+    }
+    eprintln!(
+        "✓ {} is now the tailscale operator; tailswitch won't need sudo",
+        user
+    );
+    Ok(())
+}
+
+/// Numbered text menu used instead of the TUI when stdout isn't a terminal.
+/// The menu goes to stderr so it stays visible when stdout is piped.
+fn run_plain_menu(
+    client: &TailscaleClient,
+    config: &Config,
+    options: &[TailnetOption],
+) -> Result<()> {
+    for (index, option) in options.iter().enumerate() {
+        let marker = if option.is_active { "*" } else { " " };
+        let suffix = if !option.is_profile {
+            " (add new)"
+        } else if option.needs_login {
+            " (logged out)"
+        } else {
+            ""
+        };
+        eprintln!("{:>2}. {} {}{}", index + 1, marker, option.name, suffix);
+    }
+
+    eprint!("Select a tailnet [1-{}] (Enter to cancel): ", options.len());
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read selection")?;
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    let option = input
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|index| options.get(index))
+        .with_context(|| format!("Invalid selection: {}", input))?;
+
+    let tailnet = Tailnet {
+        name: option.name.clone(),
+        ..Default::default()
+    };
+    switch_tailnet(client, config, &tailnet, false)
+        .context(Failure::SwitchFailed)
+        .and_then(require_connected)
+}
+
+/// Fail with `Failure::NeedsAuth` when a switch is still waiting on a browser login
+fn require_connected(switched: Switched) -> Result<()> {
+    if switched.connected {
+        Ok(())
+    } else {
+        Err(Failure::NeedsAuth.into())
+    }
+}
+
+/// Write existing profiles without a config entry into the config, so flags
+/// and other settings can be attached to them
+fn import_profiles(client: &TailscaleClient, mut config: Config) -> Result<()> {
+    let profiles = client.list_profiles()?;
+
+    let mut added = Vec::new();
+    for Profile { tailnet: name, .. } in profiles {
+        if !config.tailnets.iter().any(|t| t.name == name) {
+            config.tailnets.push(Tailnet {
+                name: name.clone(),
+                flags: Some(Vec::new()),
+                ..Default::default()
+            });
+            added.push(name);
+        }
+    }
+
+    if added.is_empty() {
+        println!("All profiles are already in {}", config.path().display());
+        return Ok(());
+    }
+
+    config.save()?;
+    for name in &added {
+        println!("  + {}", name);
+    }
+    println!(
+        "✓ Imported {} profile(s) into {}",
+        added.len(),
+        config.path().display()
+    );
+    Ok(())
+}
+
+/// Print the connection status for `tailswitch status`. In waybar format
+/// failures are reported as a `stopped` module rather than an error, so the
+/// bar keeps showing something useful.
+fn print_status(status: Result<StatusInfo>, format: StatusFormat) -> Result<()> {
+    if format == StatusFormat::Waybar {
+        let module = match status {
+            Ok(ref status) => {
+                let class = match status.backend_state.as_str() {
+                    "Running" => "connected",
+                    "NeedsLogin" => "logged-out",
+                    "Stopped" => "disconnected",
+                    _ => "starting",
+                };
+                let mut tooltip = status.summary();
+                if let Some(ref account) = status.login_name {
+                    tooltip = format!("{}\n{}", account, tooltip);
+                }
+                if let Some(ref exit_node) = status.exit_node {
+                    tooltip.push_str(&format!("\nExit node: {}", exit_node.name));
+                }
+                serde_json::json!({
+                    "text": status.tailnet_name.as_deref().unwrap_or(""),
+                    "tooltip": tooltip,
+                    "class": class,
+                })
+            }
+            Err(ref e) => serde_json::json!({
+                "text": "",
+                "tooltip": format!("{:#}", e).lines().next().unwrap_or_default(),
+                "class": "stopped",
+            }),
+        };
+        println!("{}", module);
+        return Ok(());
+    }
+
+    let status = status?;
+    match format {
+        StatusFormat::Json => {
+            let json = serde_json::json!({
+                "state": status.backend_state,
+                "tailnet": status.tailnet_name,
+                "account": status.login_name,
+                "ips": status.self_ips,
+                "hostname": status.self_hostname,
+                "dns_name": status.self_dns_name,
+                "magic_dns": status.magic_dns,
+                "online_peers": status.online_peers,
+                "total_peers": status.online_peers + status.offline_peers,
+                "exit_node": status.exit_node.as_ref().map(|node| &node.name),
+                "advertised_routes": status.advertised_routes,
+                "offers_exit_node": status.offers_exit_node,
+            });
+            println!("{}", json);
+        }
+        _ => match status.tailnet_name {
+            Some(ref tailnet) => println!("{} · {}", tailnet, status.summary()),
+            None => println!("{}", status.summary()),
+        },
+    }
+    Ok(())
+}
+
+/// Print the active tailnet's name for `tailswitch current` and exit: 0 when
+/// connected, 1 (printing nothing) when logged out or tailscaled is unreachable
+fn print_current(status: Option<StatusInfo>) -> ! {
+    let name = status
+        .filter(|status| !status.is_logged_out())
+        .and_then(|status| status.tailnet_name);
+    let Some(name) = name else {
+        std::process::exit(1);
+    };
+
+    if io::stdout().is_terminal() && !theme::no_color() {
+        use crossterm::style::Stylize;
+        println!("{}", name.green());
+    } else {
+        println!("{}", name);
+    }
+    std::process::exit(0);
+}
+
+/// Print the combined profile/config list as a table or JSON
+fn print_list(options: &[TailnetOption], json: bool) -> Result<()> {
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(options).context("Failed to serialize tailnet list")?
+        );
+        return Ok(());
+    }
+
+    let name_width = options
+        .iter()
+        .map(|o| o.name.chars().count())
+        .chain(std::iter::once("TAILNET".len()))
+        .max()
+        .unwrap_or(0);
+    let account_width = options
+        .iter()
+        .filter_map(|o| o.account.as_ref().map(|a| a.chars().count()))
+        .chain(std::iter::once("ACCOUNT".len()))
+        .max()
+        .unwrap_or(0);
+    let id_width = options
+        .iter()
+        .filter_map(|o| o.profile_id.as_ref().map(|id| id.chars().count()))
+        .chain(std::iter::once("ID".len()))
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "  {:<name_width$}  {:<account_width$}  {:<id_width$}  TYPE",
+        "TAILNET", "ACCOUNT", "ID"
+    );
+    for option in options {
+        println!(
+            "{} {:<name_width$}  {:<account_width$}  {:<id_width$}  {}",
+            if option.is_active { "*" } else { " " },
+            option.name,
+            option.account.as_deref().unwrap_or("-"),
+            option.profile_id.as_deref().unwrap_or("-"),
+            if option.is_profile {
+                "profile"
+            } else {
+                "config"
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Apply a tailnet's flags (as resolved by `Config::resolve_tailnet`) with
+/// `tailscale up`, returning a report of what was applied and the resulting status
+fn tailscale_up(client: &TailscaleClient, tailnet: &Tailnet) -> Result<String> {
+    client
+        .run_up(tailnet)
+        .context("Failed to run tailscale up")?;
+    if client.is_dry_run() {
+        return Ok(format!(
+            "Would update connection settings for '{}'",
+            tailnet.name
+        ));
+    }
+
+    let mut result = format!(
+        "✓ Successfully updated connection settings for '{}'!\n",
+        tailnet.name
+    );
+    if let Some(ref flags) = tailnet.flags {
+        let flags = runner::redact_secrets(&flags.join(" "));
+        result.push_str(&format!("\nApplied flags: {}\n", flags));
+    }
+
+    // Show status
+    if let Ok(status) = client.status() {
+        result.push('\n');
+        result.push_str(&status);
+    }
+    Ok(result)
+}
+
+/// Log out of the current profile
+fn logout(client: &TailscaleClient) -> Result<String> {
+    client.logout().context("Failed to logout")?;
+    if client.is_dry_run() {
+        return Ok("Would log out of the current profile".to_string());
+    }
+    Ok("✓ Successfully logged out!".to_string())
+}
+
+/// Remember the highlighted tailnet so the next run starts there
+fn remember_selection(config: &Config, selected: Option<String>) {
+    let mut state = State::load(config);
+    if selected.is_none() || state.last_selected == selected {
+        return;
+    }
+    state.last_selected = selected;
+    if let Err(e) = state.save(config) {
+        eprintln!("⚠ Failed to remember selection: {:#}", e);
+    }
+}
+
+/// Remember whether a profile (by ID) was logged out, so the list can warn next time
+fn remember_login_state(config: &Config, profile_id: &str, logged_out: bool) {
+    let mut state = State::load(config);
+    if state.logged_out.iter().any(|id| id == profile_id) == logged_out {
+        return;
+    }
+    state.set_logged_out(profile_id, logged_out);
+    if let Err(e) = state.save(config) {
+        eprintln!("⚠ Failed to save profile state: {:#}", e);
+    }
+}
+
+/// Switch to a tailnet, logging in first if the profile doesn't exist or is logged out.
+///
+/// When `interactive` is false the auth URL is printed and opened directly
+/// instead of being shown in the URL display TUI, and the `post_switch` hook's
+/// report is printed rather than returned for the TUI to show.
+fn switch_tailnet(
+    client: &TailscaleClient,
+    config: &Config,
+    tailnet: &Tailnet,
+    interactive: bool,
+) -> Result<Switched> {
+    let status = client
+        .status_json()
+        .ok()
+        .filter(|status| !status.is_logged_out());
+
+    println!("Checking existing profiles...");
+    let profiles = client.list_profiles().unwrap_or_default();
+    let profile = find_profile(&profiles, tailnet)?;
+
+    // Remembered by profile ID, so `switch -` lands on the same account
+    let current = status.as_ref().and_then(|status| {
+        profiles
+            .iter()
+            .find(|profile| profile.active)
+            .map(|profile| profile.id.clone())
+            .or_else(|| status.tailnet_name.clone())
+    });
+
+    // Given a profile ID, carry on under the profile's tailnet name
+    let by_id;
+    let tailnet = match profile {
+        Some(profile) if profile.tailnet != tailnet.name => {
+            by_id = Tailnet {
+                name: profile.tailnet.clone(),
+                profile_id: Some(profile.id.clone()),
+                ..tailnet.clone()
+            };
+            &by_id
+        }
+        _ => tailnet,
+    };
+
+    // Switching to the connected tailnet would only cost a round-trip (and maybe a sudo prompt)
+    if status.is_some() && profile.is_some_and(|profile| profile.active) {
+        println!("Already connected to {}", tailnet.name);
+        return Ok(Switched::connected(None));
+    }
+
+    println!("Switching to tailnet: {}", tailnet.name);
+
+    // Remember where we came from so `p` / `switch -` can go back
+    let remember_previous = || {
+        if let Some(ref current) = current {
+            let mut state = State::load(config);
+            state.previous_tailnet = Some(current.clone());
+            if let Err(e) = state.save(config) {
+                eprintln!("⚠ Failed to remember previous tailnet: {:#}", e);
+            }
+        }
+    };
+
+    let profile_id = profile.map(|profile| profile.id.as_str());
+
+    // Look up config for this tailnet to get flags
+    let tailnet_with_config = config.resolve_tailnet(&tailnet.name)?;
+
+    // Nothing actually changes in a dry run, so skip the status checks and hooks
+    if client.is_dry_run() {
+        if let Some(id) = profile_id {
+            client.switch_to(id)?;
+        } else {
+            client.login_and_get_url(&tailnet_with_config)?;
+        }
+        return Ok(Switched::connected(None));
+    }
+
+    if let Some(id) = profile_id {
+        // Profile exists - use fast switching
+        println!("Found existing profile for '{}'", tailnet.name);
+        println!("Switching...");
+
+        match client.switch_to(id) {
+            Ok(()) => {
+                println!("✓ Switched profile to {}", tailnet.name);
+
+                // The profile is active, but the connection (or a login prompt)
+                // can take a moment to come up
+                let info = client.wait_until_connected(config.connect_timeout()).ok();
+                let is_logged_out = info.as_ref().is_some_and(StatusInfo::is_logged_out);
+
+                remember_login_state(config, id, is_logged_out);
+
+                if is_logged_out {
+                    println!("\n⚠ Profile is logged out. Starting authentication...");
+                    // Re-authenticate with proper flags
+                    let switched = authenticate(
+                        client,
+                        &tailnet_with_config,
+                        config.theme(),
+                        interactive,
+                        TailscaleClient::login_and_get_url,
+                    )?;
+                    remember_previous();
+                    return Ok(switched);
+                }
+
+                let ip = info
+                    .as_ref()
+                    .and_then(|info| info.self_ips.first())
+                    .map(String::as_str);
+                match info {
+                    Some(ref info) if info.is_connected() => {
+                        println!(
+                            "✓ Connected to {} as {}",
+                            tailnet.name,
+                            ip.unwrap_or_default()
+                        );
+                        println!("{}", info.summary());
+                        if config.notifications_enabled() {
+                            notification::switched(&tailnet.name, ip);
+                        }
+                    }
+                    Some(ref info) => println!(
+                        "⚠ Switched to {}, but it isn't connected yet ({}). Check again with `tailswitch status`.",
+                        tailnet.name, info.backend_state
+                    ),
+                    None => println!(
+                        "⚠ Switched to {}, but couldn't read the connection status",
+                        tailnet.name
+                    ),
+                }
+                if let Ok(status) = client.status() {
+                    println!("\nCurrent status:");
+                    println!("{}", status);
+                }
+                let hook_report = post_switch(&tailnet_with_config, ip, interactive);
+                remember_previous();
+                return Ok(Switched::connected(hook_report));
+            }
+            Err(e) => {
+                eprintln!("✗ Switch failed: {}", e);
+                eprintln!("Will try logging in instead...");
+            }
+        }
+    } else {
+        println!(
+            "No existing profile for '{}'. Will log in to add it...",
+            tailnet.name
+        );
+    }
+
+    // If we get here, need to login (either profile doesn't exist or switch failed)
+    // NOTE: We do NOT logout first! Just run tailscale login/up
+    // This adds a new profile without disturbing existing ones
+    let switched = authenticate(
+        client,
+        &tailnet_with_config,
+        config.theme(),
+        interactive,
+        TailscaleClient::login_and_get_url,
+    )?;
+    remember_previous();
+    Ok(switched)
+}
+
+/// How a switch (or login) ended
+#[derive(Default)]
+struct Switched {
+    /// False while the login is waiting on the browser
+    connected: bool,
+    /// What the `post_switch` hook printed and how it exited, when one ran
+    hook_report: Option<String>,
+}
+
+impl Switched {
+    fn connected(hook_report: Option<String>) -> Self {
+        Self {
+            connected: true,
+            hook_report,
+        }
+    }
+}
+
+/// Run the tailnet's `post_switch` hook, if it has one. Outside the TUI the
+/// report is printed straight away; the TUI shows it in its output view.
+fn post_switch(tailnet: &Tailnet, ip: Option<&str>, interactive: bool) -> Option<String> {
+    let command = tailnet.post_switch.as_ref()?;
+    let report = hooks::run_post_switch(command, &tailnet.name, ip);
+    if !interactive {
+        println!("\n{}", report);
+    }
+    Some(report)
+}
+
+/// Rename a profile (by ID), refusing a name that another profile already
+/// answers to in `tailscale switch`
+fn rename_profile(client: &TailscaleClient, profile_id: &str, name: &str) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("The new name can't be empty");
+    }
+
+    let profiles = client.list_profiles()?;
+    if let Some(other) = profiles.iter().find(|profile| {
+        profile.id != profile_id
+            && (profile.account == name || profile.tailnet == name || profile.id == name)
+    }) {
+        anyhow::bail!(
+            "'{}' is already used by profile {} ({} on {})",
+            name,
+            other.id,
+            other.account,
+            other.tailnet
+        );
+    }
+
+    let active = profiles
+        .iter()
+        .find(|profile| profile.active)
+        .map(|profile| profile.id.as_str());
+    client.rename_profile(profile_id, name, active)
+}
+
+/// The existing profile to switch to for `tailnet`: the one with its
+/// `profile_id` if set, otherwise the only profile for that tailnet name (or
+/// with that ID). Fails when the name is shared by several accounts.
+fn find_profile<'a>(profiles: &'a [Profile], tailnet: &Tailnet) -> Result<Option<&'a Profile>> {
+    if let Some(ref id) = tailnet.profile_id {
+        return Ok(profiles.iter().find(|profile| &profile.id == id));
+    }
+
+    let matches: Vec<_> = profiles
+        .iter()
+        .filter(|profile| profile.tailnet == tailnet.name || profile.id == tailnet.name)
+        .collect();
+    if matches.len() > 1 {
+        let choices = matches
+            .iter()
+            .map(|profile| format!("  {}  {}", profile.id, profile.account))
+            .collect::<Vec<_>>()
+            .join("\n");
+        anyhow::bail!(
+            "Several profiles are named '{}'; switch by profile ID instead:\n{}",
+            tailnet.name,
+            choices
+        );
+    }
+    Ok(matches.into_iter().next())
+}
+
+/// Log in to a tailnet and walk the user through the auth URL if one is needed.
+/// `start` begins the login: a normal one, or a forced re-authentication.
+/// Connected only when the login finished here, i.e. no browser step was needed.
+fn authenticate(
+    client: &TailscaleClient,
+    tailnet: &Tailnet,
+    theme: Theme,
+    interactive: bool,
+    start: fn(&TailscaleClient, &Tailnet) -> Result<Option<PendingLogin>>,
+) -> Result<Switched> {
+    println!("Connecting to {}...", tailnet.name);
+    println!("Starting authentication process...");
+
+    // Auth key logins run `tailscale up` on the terminal (sudo may prompt),
+    // so only the URL wait gets a spinner
+    let login = || start(client, tailnet);
+    let result = if interactive && !tailnet.has_auth_key() {
+        ui::with_spinner(theme, "Waiting for the login URL…", login)?
+    } else {
+        login()
+    };
+
+    let login = match result.context("Failed to start tailscale connection")? {
+        Some(login) => login,
+        None => {
+            // No URL needed (auth key was used) - connection completed
+            println!("Successfully connected to {}!", tailnet.name);
+
+            // Show status
+            if let Ok(status) = client.status() {
+                println!("\nCurrent status:");
+                println!("{}", status);
+            }
+            let info = client.status_json().ok();
+            let ip = info.as_ref().and_then(|info| info.self_ips.first());
+            let hook_report = post_switch(tailnet, ip.map(String::as_str), interactive);
+            return Ok(Switched::connected(hook_report));
+        }
+    };
+
+    let url = login.url.clone();
+
+    log::debug!("Captured auth URL for {}: {}", tailnet.name, url);
+
+    let action = if interactive {
+        // We got an auth URL - show it in a TUI
+        println!("Authentication URL received. Opening URL display...");
+        let url_app = UrlDisplayApp::new(url.clone(), tailnet.name.clone(), theme);
+        url_app.run().context("Failed to run URL display")?
+    } else {
+        println!("Authentication URL: {}", url);
+        UrlAction::OpenBrowser
+    };
+
+    match action {
+        UrlAction::OpenBrowser => {}
+        UrlAction::Leave => {
+            // User pressed 'q' - exit without opening browser
+            println!("Exited without opening browser.");
+            println!("You can manually open this URL to complete authentication:");
+            println!("{}", url);
+            println!(
+                "\nTailscale is still running in the background waiting for authentication (PID {}).",
+                login.pid
+            );
+            println!("Stop it with: kill {}", login.pid);
+            return Ok(Switched::default());
+        }
+        UrlAction::Cancel => {
+            client.cancel_login(&login)?;
+            println!("✓ Login cancelled.");
+            return Ok(Switched::default());
+        }
+    }
+
+    println!("Opening browser...");
+    match browser::open_url(
+        &url,
+        tailnet.browser.as_deref(),
+        tailnet.incognito.unwrap_or(false),
+    ) {
+        Ok(()) => {
+            println!("✓ Browser launch initiated!");
+            println!("✓ Please complete authentication in your browser.");
+            println!("✓ Select the '{}' tailnet when prompted.", tailnet.name);
+            println!(
+                "\nTailscale is running in the background (PID {}).",
+                login.pid
+            );
+            println!("Run 'tailscale status' in a few moments to verify connection.");
+            println!("\nIf browser didn't open, manually open this URL:");
+            println!("{}", url);
+        }
+        Err(e) => {
+            eprintln!("✗ {:#}", e);
+            eprintln!("\nPlease manually open this URL in your browser:");
+            eprintln!("{}", url);
+        }
+    }
+
+    Ok(Switched::default())
+}
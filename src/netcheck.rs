@@ -0,0 +1,134 @@
+/// The parts of a `tailscale netcheck` report worth summarizing. Built from
+/// the text output, which (unlike `--format=json`) names the DERP regions.
+#[derive(Debug, Clone, Default)]
+pub struct NetcheckReport {
+    pub udp: Option<String>,
+    pub ipv4: Option<String>,
+    pub ipv6: Option<String>,
+    /// `MappingVariesByDestIP`: "true" means a hard (endpoint-dependent) NAT
+    pub mapping_varies: Option<String>,
+    pub port_mapping: Option<String>,
+    pub captive_portal: Option<String>,
+    pub nearest_derp: Option<String>,
+    pub derp_latencies: Vec<DerpLatency>,
+}
+
+/// Round-trip time to one DERP relay region
+#[derive(Debug, Clone, Default)]
+pub struct DerpLatency {
+    pub code: String,
+    pub name: String,
+    pub latency: String,
+}
+
+impl NetcheckReport {
+    /// Parse the `Report:` block. `None` if nothing was recognized, e.g. when
+    /// a future tailscale changes the format.
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut report = Self::default();
+        let mut found = false;
+
+        for line in output.lines() {
+            let line = line.trim();
+
+            // "- fra: 10.1ms  (Frankfurt)"
+            if let Some(entry) = line.strip_prefix("- ") {
+                if let Some((code, rest)) = entry.split_once(':') {
+                    let rest = rest.trim();
+                    let (latency, name) = match rest.split_once('(') {
+                        Some((latency, name)) => (latency.trim(), name.trim_end_matches(')')),
+                        None => (rest, ""),
+                    };
+                    report.derp_latencies.push(DerpLatency {
+                        code: code.trim().to_string(),
+                        name: name.trim().to_string(),
+                        latency: latency.to_string(),
+                    });
+                    found = true;
+                }
+                continue;
+            }
+
+            // "* UDP: true"
+            let Some((key, value)) = line
+                .strip_prefix("* ")
+                .and_then(|entry| entry.split_once(':'))
+            else {
+                continue;
+            };
+            let value = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            let field = match key.trim() {
+                "UDP" => &mut report.udp,
+                "IPv4" => &mut report.ipv4,
+                "IPv6" => &mut report.ipv6,
+                "MappingVariesByDestIP" => &mut report.mapping_varies,
+                "PortMapping" => &mut report.port_mapping,
+                "CaptivePortal" => &mut report.captive_portal,
+                "Nearest DERP" => &mut report.nearest_derp,
+                _ => continue,
+            };
+            *field = value;
+            found = true;
+        }
+
+        found.then_some(report)
+    }
+
+    /// Readable multi-line summary for the output view
+    pub fn summary(&self) -> String {
+        let unknown = || "unknown".to_string();
+        let mut lines = vec!["Connectivity".to_string()];
+        let mut row = |label: &str, value: String| lines.push(format!("  {:<15} {}", label, value));
+
+        row(
+            "UDP:",
+            match self.udp.as_deref() {
+                Some("true") => "✓ works".to_string(),
+                Some("false") => "✗ blocked - only DERP relays can be used".to_string(),
+                Some(other) => other.to_string(),
+                None => unknown(),
+            },
+        );
+        row("IPv4:", self.ipv4.clone().unwrap_or_else(unknown));
+        row("IPv6:", self.ipv6.clone().unwrap_or_else(unknown));
+        row(
+            "NAT:",
+            match self.mapping_varies.as_deref() {
+                Some("false") => "easy - direct connections should work".to_string(),
+                Some("true") => "hard - direct connections may need a relay".to_string(),
+                _ => unknown(),
+            },
+        );
+        row(
+            "Port mapping:",
+            self.port_mapping
+                .clone()
+                .unwrap_or_else(|| "none".to_string()),
+        );
+        match self.captive_portal.as_deref() {
+            Some("true") => row(
+                "Captive portal:",
+                "✗ yes - sign in to the network first".to_string(),
+            ),
+            Some("false") => row("Captive portal:", "no".to_string()),
+            _ => {}
+        }
+
+        lines.push(String::new());
+        lines.push(match self.nearest_derp {
+            Some(ref nearest) => format!("DERP relays (nearest: {})", nearest),
+            None => "DERP relays".to_string(),
+        });
+        if self.derp_latencies.is_empty() {
+            lines.push("  none reachable".to_string());
+        }
+        for derp in &self.derp_latencies {
+            lines.push(format!(
+                "  {:<6} {:<20} {}",
+                derp.code, derp.name, derp.latency
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,16 @@
+/// Show a desktop notification that a switch completed.
+///
+/// Best-effort: without a notification daemon (headless, SSH, minimal WMs)
+/// this silently does nothing.
+pub fn switched(tailnet_name: &str, ip: Option<&str>) {
+    let body = match ip {
+        Some(ip) => format!("Switched to {} · {}", tailnet_name, ip),
+        None => format!("Switched to {}", tailnet_name),
+    };
+
+    let _ = notify_rust::Notification::new()
+        .appname("tailswitch")
+        .summary("TailSwitch")
+        .body(&body)
+        .show();
+}
@@ -0,0 +1,156 @@
+use crate::config::Config;
+use crate::state::State;
+use crate::status::StatusInfo;
+use crate::tailscale::TailscaleClient;
+use serde::Serialize;
+
+/// Section for rows without a configured group, once any group is configured
+pub const DEFAULT_GROUP: &str = "Other";
+
+/// An entry in the tailnet list: an existing profile or a configured tailnet
+#[derive(Debug, Clone, Serialize)]
+pub struct TailnetOption {
+    pub name: String,
+    /// Label from the config's `display_name`, shown instead of `name`
+    pub display_name: Option<String>,
+    /// The config's `group`; rows without one are listed under `DEFAULT_GROUP`
+    pub group: Option<String>,
+    pub account: Option<String>,
+    /// Tailscale's ID for the profile, which (unlike the name) is unique
+    pub profile_id: Option<String>,
+    pub is_profile: bool,
+    pub is_active: bool,
+    /// Profile is known to be logged out, so switching to it means re-authenticating
+    pub needs_login: bool,
+}
+
+/// Build the list of options: existing profiles first, then config entries
+/// that don't exist as profiles yet
+pub fn load_options(client: &TailscaleClient, config: &Config) -> Vec<TailnetOption> {
+    let profiles = client.list_profiles().unwrap_or_default();
+
+    let mut options = Vec::new();
+
+    // Add existing profiles first
+    for profile in &profiles {
+        options.push(TailnetOption {
+            name: profile.tailnet.clone(),
+            display_name: None,
+            group: None,
+            account: Some(profile.account.clone()),
+            profile_id: Some(profile.id.clone()),
+            is_profile: true,
+            is_active: profile.active,
+            needs_login: false,
+        });
+    }
+
+    // Add config entries that don't already exist as profiles
+    options.extend(config_options(config).into_iter().filter(|option| {
+        !profiles
+            .iter()
+            .any(|profile| profile.tailnet == option.name)
+    }));
+
+    apply_config_labels(&mut options, config);
+
+    // Profiles found logged out on an earlier switch
+    let state = State::load(config);
+    for option in options.iter_mut().filter(|o| o.is_profile) {
+        option.needs_login = state.logged_out.iter().any(|id| id == option.key());
+    }
+
+    // Get current status to see which profile is active
+    mark_active(&mut options, &client.status_json().unwrap_or_default());
+
+    options
+}
+
+/// Give each row its configured `display_name` and `group`, if any
+pub fn apply_config_labels(options: &mut [TailnetOption], config: &Config) {
+    for option in options {
+        let entry = config
+            .tailnets
+            .iter()
+            .find(|tailnet| tailnet.name == option.name);
+        option.display_name = entry.and_then(|tailnet| tailnet.display_name.clone());
+        option.group = entry.and_then(|tailnet| tailnet.group.clone());
+    }
+}
+
+impl TailnetOption {
+    /// The group this row is listed under
+    pub fn group_label(&self) -> &str {
+        self.group.as_deref().unwrap_or(DEFAULT_GROUP)
+    }
+
+    /// What the list shows: the display name, or the tailnet name
+    pub fn label(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// What identifies this row: the profile ID, or the name for config
+    /// entries that have no profile yet
+    pub fn key(&self) -> &str {
+        self.profile_id.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Rows for the configured tailnets alone, for when tailscale can't be asked
+/// about profiles
+pub fn config_options(config: &Config) -> Vec<TailnetOption> {
+    config
+        .tailnets
+        .iter()
+        .map(|tailnet| TailnetOption {
+            name: tailnet.name.clone(),
+            display_name: tailnet.display_name.clone(),
+            group: tailnet.group.clone(),
+            account: None,
+            profile_id: None,
+            is_profile: false,
+            is_active: false,
+            needs_login: false,
+        })
+        .collect()
+}
+
+/// Flag the profile matching the current status as active.
+///
+/// The tailnet name comes from status; when several profiles share a tailnet
+/// name, the signed-in account tells them apart, then the profile tailscale
+/// listed as current. The current profile's login state is known for sure,
+/// so its `needs_login` is updated too.
+pub fn mark_active(options: &mut [TailnetOption], status: &StatusInfo) {
+    let candidates = || {
+        options
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| o.is_profile && status.tailnet_name.as_ref() == Some(&o.name))
+    };
+    let listed = candidates().find(|(_, o)| o.is_active).map(|(i, _)| i);
+
+    let active = if status.is_logged_out() {
+        None
+    } else {
+        candidates()
+            .find(|(_, o)| o.account.is_some() && o.account == status.login_name)
+            .map(|(i, _)| i)
+            .or(listed)
+            .or(candidates().next().map(|(i, _)| i))
+    };
+    // Without an account to go by, the profile that was current is the one that logged out
+    let logged_out = candidates()
+        .map(|(i, _)| i)
+        .filter(|&i| listed.is_none_or(|listed| listed == i))
+        .collect::<Vec<_>>();
+
+    for (i, option) in options.iter_mut().enumerate() {
+        option.is_active = active == Some(i);
+        if option.is_active {
+            option.needs_login = false;
+        } else if status.is_logged_out() && logged_out.contains(&i) {
+            option.needs_login = true;
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long a tailscale command may run before it's killed, unless configured
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Executes tailscale CLI invocations.
+///
+/// `TailscaleClient` only talks to tailscale through this trait, so its
+/// parsing logic can run against canned output instead of a real install.
+pub trait CommandRunner: Send + Sync {
+    /// Run tailscale with the given arguments and capture its output
+    fn run(&self, args: &[&str]) -> Result<Output>;
+
+    /// Run tailscale attached to the terminal (so sudo can prompt for a
+    /// password) and wait for it to exit
+    fn run_interactive(&self, args: &[&str]) -> Result<ExitStatus>;
+
+    /// Start tailscale in the background with stdout and stderr redirected
+    /// to `log_path`, without waiting for it. Returns the process ID.
+    fn spawn_logged(&self, args: &[&str], log_path: &Path) -> Result<u32>;
+
+    /// Stop a process started by `spawn_logged`, as the same user it runs as
+    fn kill(&self, pid: u32) -> Result<()>;
+
+    /// Start tailscale in the background and deliver its output line by line
+    fn spawn_streaming(&self, args: &[&str]) -> Result<Streaming>;
+
+    /// A runner for the same tailscale and timeout, with or without sudo
+    fn with_sudo(&self, use_sudo: bool) -> Box<dyn CommandRunner>;
+
+    /// The command line that running `args` would execute, for display
+    fn describe(&self, args: &[&str]) -> String {
+        let command = std::iter::once("tailscale")
+            .chain(args.iter().copied())
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        redact_secrets(&command)
+    }
+}
+
+/// Runs the real tailscale binary, optionally through sudo. Commands whose
+/// output is read (`run`, `spawn_streaming`) are killed once they run longer
+/// than `timeout` (`None` waits forever). `run_interactive` has no limit,
+/// since the user may be typing a password or finishing a browser login.
+pub struct SystemRunner {
+    use_sudo: bool,
+    binary: String,
+    timeout: Option<Duration>,
+}
+
+impl SystemRunner {
+    pub fn new(use_sudo: bool, binary: &str, timeout: Option<Duration>) -> Self {
+        Self {
+            use_sudo,
+            binary: binary.to_string(),
+            timeout,
+        }
+    }
+
+    fn create_command(&self) -> Command {
+        if self.use_sudo {
+            let mut cmd = Command::new("sudo");
+            cmd.arg(&self.binary);
+            cmd
+        } else {
+            Command::new(&self.binary)
+        }
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, args: &[&str]) -> Result<Output> {
+        log::debug!("Running {}", self.describe(args));
+        let started = Instant::now();
+        let child = self
+            .create_command()
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to execute tailscale {}", display_args(args)))?;
+
+        let output = output_with_timeout(child, self.timeout)
+            .with_context(|| format!("tailscale {} did not finish", display_args(args)))?;
+        log::debug!(
+            "tailscale {} finished ({}) after {:?}",
+            display_args(args),
+            output.status,
+            started.elapsed()
+        );
+        for (name, stream) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+            if !stream.is_empty() {
+                log::trace!("{}: {}", name, String::from_utf8_lossy(stream).trim_end());
+            }
+        }
+        Ok(output)
+    }
+
+    fn run_interactive(&self, args: &[&str]) -> Result<ExitStatus> {
+        log::debug!("Running {} on the terminal", self.describe(args));
+        let started = Instant::now();
+        // Use spawn + wait instead of output to allow sudo password prompt
+        let mut child = self
+            .create_command()
+            .args(args)
+            .spawn()
+            .with_context(|| format!("Failed to execute tailscale {}", display_args(args)))?;
+
+        let status = child
+            .wait()
+            .with_context(|| format!("tailscale {} did not finish", display_args(args)))?;
+        log::debug!(
+            "tailscale {} finished ({}) after {:?}",
+            display_args(args),
+            status,
+            started.elapsed()
+        );
+        Ok(status)
+    }
+
+    fn spawn_logged(&self, args: &[&str], log_path: &Path) -> Result<u32> {
+        let log = File::create(log_path).context("Failed to open log file")?;
+
+        let mut cmd = self.create_command();
+        cmd.args(args)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone().context("Failed to open log file")?)
+            .stderr(log);
+
+        // Own process group, so Ctrl-C in the terminal doesn't take it down
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+        let child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to start tailscale {}", display_args(args)))?;
+        log::debug!(
+            "Started {} in the background (PID {}), logging to {}",
+            self.describe(args),
+            child.id(),
+            log_path.display()
+        );
+
+        Ok(child.id())
+    }
+
+    fn kill(&self, pid: u32) -> Result<()> {
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("taskkill");
+            cmd.args(["/PID", &pid.to_string(), "/T", "/F"]);
+            cmd
+        } else {
+            // sudo's child runs as root, so only root can signal it; spawn_logged
+            // made the process its own group leader, so this stops the whole group
+            let mut cmd = if self.use_sudo {
+                let mut cmd = Command::new("sudo");
+                cmd.arg("kill");
+                cmd
+            } else {
+                Command::new("kill")
+            };
+            cmd.args(["--", &format!("-{}", pid)]);
+            cmd
+        };
+        log::debug!("Stopping background tailscale (PID {})", pid);
+
+        let status = cmd.status().context("Failed to run kill")?;
+        if !status.success() {
+            anyhow::bail!("kill exited with {}", status);
+        }
+        Ok(())
+    }
+
+    fn spawn_streaming(&self, args: &[&str]) -> Result<Streaming> {
+        let child = self
+            .create_command()
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start tailscale {}", display_args(args)))?;
+        log::debug!("Streaming {} (PID {})", self.describe(args), child.id());
+
+        Ok(Streaming::new(child, self.timeout))
+    }
+
+    fn with_sudo(&self, use_sudo: bool) -> Box<dyn CommandRunner> {
+        Box::new(SystemRunner::new(use_sudo, &self.binary, self.timeout))
+    }
+
+    fn describe(&self, args: &[&str]) -> String {
+        let sudo = self.use_sudo.then_some("sudo");
+        let command = sudo
+            .into_iter()
+            .chain(std::iter::once(self.binary.as_str()))
+            .chain(args.iter().copied())
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        redact_secrets(&command)
+    }
+}
+
+/// A user-supplied command line run through the platform shell: `sh -c` on
+/// Unix, `cmd /C` on Windows
+pub fn shell_command(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut cmd = Command::new(shell);
+    cmd.arg(flag).arg(command);
+    cmd
+}
+
+/// A command running in the background whose stdout and stderr arrive line
+/// by line. The process is killed when this is dropped, or once it has run
+/// past its timeout.
+pub struct Streaming {
+    child: Child,
+    lines: Receiver<String>,
+    readers: Vec<JoinHandle<()>>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl Streaming {
+    fn new(mut child: Child, timeout: Option<Duration>) -> Self {
+        let (sender, lines) = mpsc::channel();
+
+        let mut readers = Vec::new();
+        let stdout = child
+            .stdout
+            .take()
+            .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>);
+        let stderr = child
+            .stderr
+            .take()
+            .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>);
+        for pipe in stdout.into_iter().chain(stderr) {
+            let sender = sender.clone();
+            readers.push(std::thread::spawn(move || {
+                for line in BufReader::new(pipe).lines().map_while(|line| line.ok()) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+
+        Self {
+            child,
+            lines,
+            readers,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            timed_out: false,
+        }
+    }
+
+    /// Lines printed since the last call, without blocking
+    pub fn new_lines(&self) -> Vec<String> {
+        self.lines.try_iter().collect()
+    }
+
+    /// The exit status once the process has finished and all of its output
+    /// has been read; call `new_lines` afterwards for the last lines
+    pub fn try_finish(&mut self) -> Option<ExitStatus> {
+        if !self.timed_out
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            && matches!(self.child.try_wait(), Ok(None))
+        {
+            log::debug!("Killing PID {} after its timeout", self.child.id());
+            let _ = self.child.kill();
+            self.timed_out = true;
+        }
+        if !self.readers.iter().all(JoinHandle::is_finished) {
+            return None;
+        }
+        self.child.try_wait().ok().flatten()
+    }
+
+    /// Whether the process was killed for running past its timeout
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+impl Drop for Streaming {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Replace auth key values in a command line (or any text showing one) with
+/// `****`, for both `--auth-key KEY` and `--auth-key=KEY`
+pub fn redact_secrets(text: &str) -> String {
+    let re = Regex::new(r"(--auth-?key)(=|\s+)('[^']*'|\S+)").expect("valid regex");
+    re.replace_all(text, "$1$2****").into_owned()
+}
+
+/// Arguments joined for error messages, with secrets redacted
+fn display_args(args: &[&str]) -> String {
+    redact_secrets(&args.join(" "))
+}
+
+/// Quote an argument for display so the printed command can be pasted into a shell
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Arguments quoted and joined with spaces, the inverse of [`shell_split`]
+pub fn shell_join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Split text into words the way a POSIX shell would, honouring single and
+/// double quotes and backslash escapes. An unterminated quote runs to the end.
+pub fn shell_split(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                word.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(next @ ('"' | '\\' | '$' | '`')) => word.push(next),
+                            Some(next) => word.extend(['\\', next]),
+                            None => word.push('\\'),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_default().extend(chars.next()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Wait for a child, killing it once `timeout` has passed
+pub fn wait_with_timeout(child: &mut Child, timeout: Option<Duration>) -> Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        return Ok(child.wait()?);
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!(
+                "tailscale command timed out after {}s (is tailscaled responding?)",
+                timeout.as_secs()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Like `Child::wait_with_output`, but gives up after `timeout`. The pipes are
+/// drained on separate threads so a chatty command can't block on a full pipe.
+pub fn output_with_timeout(mut child: Child, timeout: Option<Duration>) -> Result<Output> {
+    fn drain(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<Vec<u8>> {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buffer);
+            }
+            buffer
+        })
+    }
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = wait_with_timeout(&mut child, timeout)?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        shell_split(text)
+    }
+
+    #[test]
+    fn shell_split_separates_on_whitespace() {
+        assert_eq!(
+            words("  --ssh\t--accept-routes "),
+            ["--ssh", "--accept-routes"]
+        );
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn shell_split_keeps_quoted_spaces() {
+        assert_eq!(
+            words("--hostname='my laptop' --advertise-tags=\"tag:a tag:b\""),
+            ["--hostname=my laptop", "--advertise-tags=tag:a tag:b"]
+        );
+        assert_eq!(words(r"--hostname=my\ laptop"), ["--hostname=my laptop"]);
+        assert_eq!(words("--hostname ''"), ["--hostname", ""]);
+    }
+
+    #[test]
+    fn shell_split_handles_escapes_inside_quotes() {
+        assert_eq!(words(r#""a \"b\" \c""#), [r#"a "b" \c"#]);
+        assert_eq!(words(r"'it'\''s'"), ["it's"]);
+        assert_eq!(words("'unterminated value"), ["unterminated value"]);
+    }
+
+    #[test]
+    fn shell_join_round_trips() {
+        let args: Vec<String> = [
+            "--ssh",
+            "--hostname=my laptop",
+            "it's",
+            "",
+            "--auth-key=tskey-1",
+        ]
+        .map(str::to_string)
+        .into();
+        let joined = shell_join(&args);
+        assert_eq!(
+            joined,
+            r"--ssh '--hostname=my laptop' 'it'\''s' '' --auth-key=tskey-1"
+        );
+        assert_eq!(shell_split(&joined), args);
+    }
+}
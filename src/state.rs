@@ -0,0 +1,93 @@
+use crate::config::Config;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Small bits of state remembered between runs, stored next to the config
+/// file and named after it: `config.state.toml` for `config.toml`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct State {
+    /// Profile ID (or tailnet name) that was active before the most recent switch
+    pub previous_tailnet: Option<String>,
+    /// Profile ID (or tailnet name) highlighted when the TUI was last closed
+    pub last_selected: Option<String>,
+    /// IDs of profiles that were logged out the last time we switched to them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logged_out: Vec<String>,
+    /// Whether the offer to set the user as tailscale operator was turned down
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub declined_operator: bool,
+}
+
+impl State {
+    /// Load the state, falling back to empty state if it's missing or unreadable
+    pub fn load(config: &Config) -> Self {
+        let read = |path: PathBuf| fs::read_to_string(path).ok();
+        read(Self::path(config.path()))
+            .or_else(|| Self::legacy_path(config.path()).and_then(read))
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record whether a profile needed to log in when last switched to
+    pub fn set_logged_out(&mut self, profile_id: &str, logged_out: bool) {
+        self.logged_out.retain(|id| id != profile_id);
+        if logged_out {
+            self.logged_out.push(profile_id.to_string());
+        }
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let contents = toml::to_string_pretty(self).context("Failed to serialize state")?;
+        fs::write(Self::path(config.path()), contents).context("Failed to write state file")
+    }
+
+    /// Named after the config file, so configs sharing a directory keep
+    /// separate state
+    fn path(config_path: &Path) -> PathBuf {
+        let mut name = config_path
+            .file_stem()
+            .unwrap_or("config".as_ref())
+            .to_os_string();
+        name.push(".state.toml");
+        config_path.with_file_name(name)
+    }
+
+    /// Where the default `config.toml` kept its state before state files were
+    /// named after the config, read until the state is next saved
+    fn legacy_path(config_path: &Path) -> Option<PathBuf> {
+        (config_path.file_name()? == "config.toml")
+            .then(|| config_path.with_file_name("state.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_is_named_after_the_config() {
+        assert_eq!(
+            State::path(Path::new("/etc/tailswitch/config.toml")),
+            Path::new("/etc/tailswitch/config.state.toml")
+        );
+        assert_eq!(
+            State::path(Path::new("/home/me/work.toml")),
+            Path::new("/home/me/work.state.toml")
+        );
+        assert_eq!(
+            State::path(Path::new("/home/me/tailswitch")),
+            Path::new("/home/me/tailswitch.state.toml")
+        );
+    }
+
+    #[test]
+    fn only_the_default_config_reads_legacy_state() {
+        assert_eq!(
+            State::legacy_path(Path::new("/home/me/config.toml")),
+            Some(PathBuf::from("/home/me/state.toml"))
+        );
+        assert_eq!(State::legacy_path(Path::new("/home/me/work.toml")), None);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::datetime;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+/// Structured view of `tailscale status --json`
+#[derive(Debug, Clone, Default)]
+pub struct StatusInfo {
+    /// Backend state reported by tailscaled (e.g. "Running", "NeedsLogin", "Stopped")
+    pub backend_state: String,
+    /// Name of the tailnet the current profile belongs to
+    pub tailnet_name: Option<String>,
+    /// Login name of the account the current profile is signed in as
+    pub login_name: Option<String>,
+    /// Tailscale IPs assigned to this node
+    pub self_ips: Vec<String>,
+    /// This node's hostname
+    pub self_hostname: Option<String>,
+    /// This node's MagicDNS name, e.g. "laptop.tail1234.ts.net"
+    pub self_dns_name: Option<String>,
+    /// Whether MagicDNS is enabled for the tailnet
+    pub magic_dns: bool,
+    pub online_peers: usize,
+    pub offline_peers: usize,
+    /// Peer currently used as exit node, if any
+    pub exit_node: Option<ExitNodeStatus>,
+    /// Subnet routes this node serves to the tailnet. Only approved routes
+    /// show up here; ones still awaiting approval don't.
+    pub advertised_routes: Vec<String>,
+    /// Whether this node is offered to the tailnet as an exit node
+    pub offers_exit_node: bool,
+    pub peers: Vec<PeerStatus>,
+    /// Problems reported by tailscaled, e.g. unreachable DNS or an available update
+    pub health: Vec<HealthWarning>,
+    /// Newer tailscale release, when tailscaled knows that one exists
+    pub latest_version: Option<String>,
+    /// When this node's key expires; `None` if key expiry is disabled
+    pub key_expiry: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HealthWarning {
+    pub message: String,
+    /// Connectivity problems, as opposed to informational notices
+    pub severe: bool,
+}
+
+impl HealthWarning {
+    fn new(message: String) -> Self {
+        const SEVERE_HINTS: [&str; 7] = [
+            "error",
+            "fail",
+            "could not",
+            "unable",
+            "not running",
+            "expired",
+            "unreachable",
+        ];
+        let lower = message.to_lowercase();
+        let severe = SEVERE_HINTS.iter().any(|hint| lower.contains(hint));
+        Self { message, severe }
+    }
+}
+
+/// One other node in the tailnet
+#[derive(Debug, Clone, Default)]
+pub struct PeerStatus {
+    pub hostname: String,
+    pub ips: Vec<String>,
+    pub os: String,
+    pub online: bool,
+    /// How traffic to the peer flows; `None` while there's no connection to
+    /// it (offline, or idle with nothing sent recently)
+    pub connection: Option<PeerConnection>,
+}
+
+/// The path traffic to a peer takes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerConnection {
+    /// Straight to the peer
+    Direct,
+    /// Through a DERP relay server, in this region (e.g. "fra")
+    Relayed(String),
+}
+
+impl fmt::Display for PeerConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerConnection::Direct => f.write_str("direct"),
+            PeerConnection::Relayed(region) => write!(f, "relay ({})", region),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExitNodeStatus {
+    pub name: String,
+    pub ips: Vec<String>,
+}
+
+// Raw JSON shape - only the fields we care about
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawStatus {
+    #[serde(default)]
+    backend_state: String,
+    #[serde(rename = "TailscaleIPs", default)]
+    tailscale_ips: Option<Vec<String>>,
+    #[serde(default)]
+    current_tailnet: Option<RawTailnet>,
+    #[serde(default)]
+    peer: Option<HashMap<String, RawPeer>>,
+    #[serde(rename = "Self", default)]
+    self_node: Option<RawSelf>,
+    #[serde(default)]
+    user: Option<HashMap<String, RawUser>>,
+    #[serde(default)]
+    health: Option<Vec<String>>,
+    #[serde(default)]
+    client_version: Option<RawClientVersion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawClientVersion {
+    #[serde(default)]
+    running_latest: bool,
+    #[serde(default)]
+    latest_version: String,
+}
+
+#[derive(Deserialize)]
+struct RawSelf {
+    #[serde(rename = "UserID", default)]
+    user_id: u64,
+    #[serde(rename = "KeyExpiry", default)]
+    key_expiry: Option<String>,
+    #[serde(rename = "HostName", default)]
+    host_name: String,
+    #[serde(rename = "DNSName", default)]
+    dns_name: String,
+    #[serde(rename = "PrimaryRoutes", default)]
+    primary_routes: Option<Vec<String>>,
+    #[serde(rename = "AllowedIPs", default)]
+    allowed_ips: Option<Vec<String>>,
+    #[serde(rename = "ExitNodeOption", default)]
+    exit_node_option: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawUser {
+    #[serde(default)]
+    login_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawTailnet {
+    name: String,
+    #[serde(rename = "MagicDNSEnabled", default)]
+    magic_dns_enabled: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawPeer {
+    #[serde(default)]
+    host_name: String,
+    #[serde(rename = "DNSName", default)]
+    dns_name: String,
+    #[serde(rename = "TailscaleIPs", default)]
+    tailscale_ips: Option<Vec<String>>,
+    #[serde(rename = "OS", default)]
+    os: String,
+    #[serde(default)]
+    online: bool,
+    #[serde(default)]
+    exit_node: bool,
+    /// Home DERP region, used for traffic while there's no direct path
+    #[serde(default)]
+    relay: String,
+    /// Address of the direct path, empty when there isn't one
+    #[serde(default)]
+    cur_addr: String,
+    /// Whether traffic was exchanged with the peer recently
+    #[serde(default)]
+    active: bool,
+}
+
+impl RawPeer {
+    /// MagicDNS name without the trailing dot, falling back to the hostname
+    fn display_name(&self) -> String {
+        let dns_name = self.dns_name.trim_end_matches('.');
+        if dns_name.is_empty() {
+            self.host_name.clone()
+        } else {
+            dns_name.to_string()
+        }
+    }
+
+    /// Read the path like `tailscale status` does: direct when there's a
+    /// current address, relayed when active without one
+    fn connection(&self) -> Option<PeerConnection> {
+        if !self.online {
+            None
+        } else if !self.cur_addr.is_empty() {
+            Some(PeerConnection::Direct)
+        } else if self.active && !self.relay.is_empty() {
+            Some(PeerConnection::Relayed(self.relay.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+impl StatusInfo {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let raw: RawStatus =
+            serde_json::from_str(json).context("Failed to parse tailscale status JSON")?;
+
+        let peers = raw.peer.unwrap_or_default();
+        let online_peers = peers.values().filter(|p| p.online).count();
+        let exit_node = peers
+            .values()
+            .find(|p| p.exit_node)
+            .map(|p| ExitNodeStatus {
+                name: p.display_name(),
+                ips: p.tailscale_ips.clone().unwrap_or_default(),
+            });
+
+        let key_expiry = raw
+            .self_node
+            .as_ref()
+            .and_then(|node| node.key_expiry.as_deref())
+            .and_then(datetime::parse_rfc3339);
+
+        let self_hostname = raw
+            .self_node
+            .as_ref()
+            .map(|node| node.host_name.clone())
+            .filter(|name| !name.is_empty());
+        let self_dns_name = raw
+            .self_node
+            .as_ref()
+            .map(|node| node.dns_name.trim_end_matches('.').to_string())
+            .filter(|name| !name.is_empty());
+
+        // AllowedIPs holds our own addresses plus every approved route, with
+        // the default routes standing for the exit node offer
+        let self_ips = raw.tailscale_ips.unwrap_or_default();
+        let mut advertised_routes = Vec::new();
+        let mut offers_exit_node = false;
+        if let Some(node) = raw.self_node.as_ref() {
+            offers_exit_node = node.exit_node_option;
+            for route in node
+                .allowed_ips
+                .iter()
+                .flatten()
+                .chain(node.primary_routes.iter().flatten())
+            {
+                let (address, prefix) = route.split_once('/').unwrap_or((route, ""));
+                let own_address =
+                    (prefix == "32" || prefix == "128") && self_ips.iter().any(|ip| ip == address);
+                if is_default_route(route) {
+                    offers_exit_node = true;
+                } else if !own_address && !advertised_routes.contains(route) {
+                    advertised_routes.push(route.clone());
+                }
+            }
+        }
+
+        let login_name = raw.self_node.and_then(|node| {
+            raw.user
+                .unwrap_or_default()
+                .remove(&node.user_id.to_string())
+                .map(|user| user.login_name)
+                .filter(|name| !name.is_empty())
+        });
+
+        let peer_list = peers
+            .values()
+            .map(|p| PeerStatus {
+                hostname: p.host_name.clone(),
+                ips: p.tailscale_ips.clone().unwrap_or_default(),
+                os: p.os.clone(),
+                online: p.online,
+                connection: p.connection(),
+            })
+            .collect();
+
+        let latest_version = raw
+            .client_version
+            .filter(|version| !version.running_latest && !version.latest_version.is_empty())
+            .map(|version| version.latest_version);
+
+        Ok(Self {
+            backend_state: raw.backend_state,
+            magic_dns: raw
+                .current_tailnet
+                .as_ref()
+                .is_some_and(|t| t.magic_dns_enabled),
+            tailnet_name: raw.current_tailnet.map(|t| t.name),
+            login_name,
+            self_ips,
+            self_hostname,
+            self_dns_name,
+            online_peers,
+            offline_peers: peers.len() - online_peers,
+            exit_node,
+            advertised_routes,
+            offers_exit_node,
+            peers: peer_list,
+            health: raw
+                .health
+                .unwrap_or_default()
+                .into_iter()
+                .map(HealthWarning::new)
+                .collect(),
+            latest_version,
+            key_expiry,
+        })
+    }
+
+    /// Time left before the node key expires (zero once it has), if that's
+    /// within `window`
+    pub fn key_expires_within(&self, window: Duration) -> Option<Duration> {
+        let left = self
+            .key_expiry?
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        (left <= window).then_some(left)
+    }
+
+    /// Whether the current profile needs to log in before it can connect
+    pub fn is_logged_out(&self) -> bool {
+        self.backend_state == "NeedsLogin"
+    }
+
+    /// Whether the backend is up with an address, i.e. actually connected
+    pub fn is_connected(&self) -> bool {
+        self.backend_state == "Running" && !self.self_ips.is_empty()
+    }
+
+    /// Whether a tailscale update is available, from the reported latest
+    /// version or a health message saying so
+    pub fn update_available(&self) -> bool {
+        self.latest_version.is_some()
+            || self.health.iter().any(|warning| {
+                let message = warning.message.to_lowercase();
+                message.contains("update") && message.contains("available")
+            })
+    }
+
+    /// Number of peers connected (directly, through a relay)
+    pub fn connection_counts(&self) -> (usize, usize) {
+        self.peers
+            .iter()
+            .fold((0, 0), |(direct, relayed), peer| match peer.connection {
+                Some(PeerConnection::Direct) => (direct + 1, relayed),
+                Some(PeerConnection::Relayed(_)) => (direct, relayed + 1),
+                None => (direct, relayed),
+            })
+    }
+
+    /// Compare `configured` advertise_routes with the routes in effect:
+    /// (configured but not advertised, advertised but not configured)
+    pub fn route_differences(&self, configured: &[String]) -> (Vec<String>, Vec<String>) {
+        let advertised: Vec<String> = self
+            .advertised_routes
+            .iter()
+            .map(|route| canonical_route(route))
+            .collect();
+        let configured: Vec<String> = configured
+            .iter()
+            .map(|route| canonical_route(route))
+            .collect();
+        let missing = configured
+            .iter()
+            .filter(|route| !advertised.contains(route))
+            .cloned()
+            .collect();
+        let extra = advertised
+            .iter()
+            .filter(|route| !configured.contains(route))
+            .cloned()
+            .collect();
+        (missing, extra)
+    }
+
+    /// One-line summary, e.g. "Running · 100.64.0.1 · 3/5 peers online"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.backend_state.clone()];
+        if let Some(ip) = self.self_ips.first() {
+            parts.push(ip.clone());
+        }
+        parts.push(format!(
+            "{}/{} peers online",
+            self.online_peers,
+            self.online_peers + self.offline_peers
+        ));
+        parts.join(" · ")
+    }
+}
+
+fn is_default_route(route: &str) -> bool {
+    route == "0.0.0.0/0" || route == "::/0"
+}
+
+/// A route with its address in canonical form, so "fd00:0::/64" and
+/// "fd00::/64" compare equal
+fn canonical_route(route: &str) -> String {
+    match route.split_once('/') {
+        Some((address, prefix)) => match address.parse::<IpAddr>() {
+            Ok(address) => format!("{}/{}", address, prefix),
+            Err(_) => route.to_string(),
+        },
+        None => route.to_string(),
+    }
+}
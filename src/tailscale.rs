@@ -1,204 +1,430 @@
 use crate::config::Tailnet;
+use crate::localapi::LocalApi;
+use crate::runner::{self, CommandRunner, Streaming, SystemRunner};
+use crate::status::StatusInfo;
 use anyhow::{Context, Result};
-use std::process::Command;
+use notify::{RecursiveMode, Watcher};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, PoisonError, mpsc};
+use std::time::{Duration, Instant};
+
+/// How long to wait for `tailscale login` to print the auth URL
+const LOGIN_URL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to re-read the login log when file watching isn't available
+const LOGIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Tries for read-only commands, which fail spuriously while tailscaled restarts
+const READ_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after
+const READ_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// How often to re-read status while waiting for a connection
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// CLI inside the macOS app bundle, used when `tailscale` isn't on PATH
+const MACOS_APP_BINARY: &str = "/Applications/Tailscale.app/Contents/MacOS/Tailscale";
+
+/// Control server host used when a tailnet has no custom login_server
+const DEFAULT_CONTROL_HOST: &str = "login.tailscale.com";
+
+/// Subset of `tailscale debug prefs`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Prefs {
+    #[serde(rename = "OperatorUser", default)]
+    pub operator_user: String,
+    /// Whether subnet routes from other nodes are accepted (`--accept-routes`)
+    #[serde(rename = "RouteAll", default)]
+    pub accept_routes: bool,
+    /// Whether the tailnet DNS settings are used (`--accept-dns`)
+    #[serde(rename = "CorpDNS", default)]
+    pub accept_dns: bool,
+}
+
+/// An entry from `tailscale exit-node list`
+#[derive(Debug, Clone)]
+pub struct ExitNode {
+    pub ip: String,
+    pub hostname: String,
+    pub country: String,
+    pub city: String,
+    pub status: String,
+}
+
+/// An existing login profile, as listed by `tailscale switch --list`.
+/// Several accounts can share a tailnet name; the ID tells them apart.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub id: String,
+    pub tailnet: String,
+    pub account: String,
+    /// The profile tailscale is currently using
+    pub active: bool,
+}
+
+/// A `tailscale login` running in the background, waiting for the user to
+/// visit `url`
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pub url: String,
+    pub pid: u32,
+}
 
 pub struct TailscaleClient {
-    use_sudo: bool,
+    runner: Box<dyn CommandRunner>,
+    /// Used for reads when tailscaled's socket is reachable, with the CLI as fallback
+    local_api: Option<LocalApi>,
+    /// Result of the last `switch --list`, kept until the profiles change
+    profiles: Mutex<Option<Vec<Profile>>>,
+    /// When set, commands that change anything are recorded here instead of run
+    dry_run: Option<Mutex<Vec<String>>>,
 }
 
 impl TailscaleClient {
-    pub fn new(use_sudo: bool) -> Self {
-        Self { use_sudo }
+    pub fn new(use_sudo: bool, binary: &str, timeout: Option<Duration>) -> Self {
+        Self {
+            local_api: LocalApi::detect(timeout),
+            ..Self::with_runner(Box::new(SystemRunner::new(use_sudo, binary, timeout)))
+        }
     }
 
-    fn create_command(&self) -> Command {
-        if self.use_sudo {
-            let mut cmd = Command::new("sudo");
-            cmd.arg("tailscale");
-            cmd
-        } else {
-            Command::new("tailscale")
+    /// The same client with or without sudo. Cheap: the binary, timeout and
+    /// LocalAPI socket are shared rather than looked up again. The copy starts
+    /// with an empty profile cache and without dry-run mode.
+    pub fn clone_with_sudo(&self, use_sudo: bool) -> Self {
+        Self {
+            runner: self.runner.with_sudo(use_sudo),
+            local_api: self.local_api.clone(),
+            profiles: Mutex::new(None),
+            dry_run: None,
         }
     }
 
-    /// Logout from current tailnet
-    pub fn logout(&self) -> Result<()> {
-        let mut cmd = self.create_command();
-        cmd.arg("logout");
+    /// Create a client that executes tailscale through the given runner
+    pub fn with_runner(runner: Box<dyn CommandRunner>) -> Self {
+        Self {
+            runner,
+            local_api: None,
+            profiles: Mutex::new(None),
+            dry_run: None,
+        }
+    }
 
-        // Use spawn + wait instead of output to allow sudo password prompt
-        let status = cmd
-            .spawn()
-            .context("Failed to execute tailscale logout")?
-            .wait()
-            .context("Failed to wait for tailscale logout")?;
+    /// Only record commands that would change tailscale's state (`up`,
+    /// `login`, `switch`, ...) instead of running them. Reads still run.
+    pub fn with_dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = enabled.then(|| Mutex::new(Vec::new()));
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.is_some()
+    }
+
+    /// Commands skipped because of `--dry-run` since the last call
+    pub fn take_dry_run_commands(&self) -> Vec<String> {
+        self.dry_run
+            .as_ref()
+            .map(|planned| {
+                std::mem::take(&mut *planned.lock().unwrap_or_else(PoisonError::into_inner))
+            })
+            .unwrap_or_default()
+    }
+
+    /// In dry-run mode, record the command and return true so the caller skips it
+    fn skip_for_dry_run(&self, args: &[&str]) -> bool {
+        let Some(ref planned) = self.dry_run else {
+            return false;
+        };
+        planned
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(self.runner.describe(args));
+        true
+    }
+
+    /// The tailnet's auth key. A dry run gets a placeholder, so the key source
+    /// (a file, or a command that may prompt or go online) is left alone.
+    fn auth_key(&self, tailnet: &Tailnet) -> Result<Option<String>> {
+        if self.is_dry_run() && tailnet.has_auth_key() {
+            return Ok(Some("AUTH_KEY".to_string()));
+        }
+        tailnet.resolve_auth_key()
+    }
+
+    /// Run a command attached to the terminal, failing on a non-zero exit
+    fn run_interactive(&self, args: &[&str], what: &str) -> Result<()> {
+        if self.skip_for_dry_run(args) {
+            return Ok(());
+        }
+
+        let status = self.runner.run_interactive(args)?;
 
         if !status.success() {
-            anyhow::bail!(
-                "Tailscale logout failed with exit code: {:?}",
-                status.code()
+            anyhow::bail!("{} failed with exit code: {:?}", what, status.code());
+        }
+
+        Ok(())
+    }
+
+    /// Run a read-only command, retrying with backoff while tailscaled can't
+    /// be reached (e.g. mid-restart). Other failures, and the last attempt's
+    /// output, are returned as they are.
+    fn run_read(&self, args: &[&str]) -> Result<Output> {
+        let mut delay = READ_RETRY_DELAY;
+        for _ in 1..READ_ATTEMPTS {
+            let output = self.runner.run(args)?;
+            if output.status.success() || !is_transient_failure(&output.stderr) {
+                return Ok(output);
+            }
+            log::debug!(
+                "{} failed transiently, retrying in {:?}",
+                self.runner.describe(args),
+                delay
             );
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+        self.runner.run(args)
+    }
+
+    /// Query the LocalAPI, or `None` if it isn't available (no socket,
+    /// permission denied, ...) so the caller falls back to the CLI
+    fn local_api_get(&self, endpoint: &str) -> Option<String> {
+        let started = Instant::now();
+        match self.local_api.as_ref()?.get(endpoint) {
+            Ok(body) => {
+                log::debug!("LocalAPI {} answered in {:?}", endpoint, started.elapsed());
+                Some(body)
+            }
+            Err(e) => {
+                log::debug!("LocalAPI {} failed, using the CLI: {:#}", endpoint, e);
+                None
+            }
+        }
+    }
+
+    /// Logout from current tailnet
+    pub fn logout(&self) -> Result<()> {
+        self.invalidate_profiles();
+        self.run_interactive(&["logout"], "Tailscale logout")
+    }
+
+    /// Remove a profile, given its ID and the active profile's ID.
+    ///
+    /// Tailscale only removes the current profile on logout, so switch to it
+    /// first and then back to the previously active profile afterwards.
+    pub fn remove_profile(&self, profile_id: &str, active: Option<&str>) -> Result<()> {
+        if active != Some(profile_id) {
+            self.switch_to(profile_id)?;
+        }
+
+        self.logout()?;
+
+        if let Some(previous) = active.filter(|id| *id != profile_id) {
+            self.switch_to(previous).with_context(|| {
+                format!("Profile removed, but failed to switch back to {}", previous)
+            })?;
         }
 
         Ok(())
     }
 
-    /// Get list of existing tailscale profiles
-    pub fn list_profiles(&self) -> Result<Vec<(String, String)>> {
-        let mut cmd = self.create_command();
-        cmd.arg("switch");
-        cmd.arg("--list");
+    /// Rename a profile, given its ID and the active profile's ID. The new
+    /// name replaces the account name in `tailscale switch --list`.
+    ///
+    /// `tailscale set --nickname` only renames the current profile, so switch
+    /// to it first and back afterwards, as with `remove_profile`.
+    pub fn rename_profile(&self, profile_id: &str, name: &str, active: Option<&str>) -> Result<()> {
+        if active != Some(profile_id) {
+            self.switch_to(profile_id)?;
+        }
 
-        let output = cmd
-            .output()
-            .context("Failed to execute tailscale switch --list")?;
+        let renamed = self.set(&format!("--nickname={}", name));
+        self.invalidate_profiles();
 
-        if !output.status.success() {
-            anyhow::bail!("Failed to list profiles");
+        if let Some(previous) = active.filter(|id| *id != profile_id) {
+            self.switch_to(previous)
+                .with_context(|| format!("Failed to switch back to {} after renaming", previous))?;
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut profiles = Vec::new();
-
-        // Parse output (skip header line)
-        for line in stdout.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                // Format: ID    Tailnet             Account
-                let tailnet = parts[1].to_string();
-                let account = if parts.len() > 2 {
-                    // Keep the * in the account field - we use it to detect active profile
-                    parts[2].to_string()
-                } else {
-                    String::new()
-                };
-                profiles.push((tailnet, account));
-            }
+        renamed
+    }
+
+    /// Get list of existing tailscale profiles, cached after the first call
+    pub fn list_profiles(&self) -> Result<Vec<Profile>> {
+        let mut cached = self.profiles.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ref profiles) = *cached {
+            return Ok(profiles.clone());
         }
 
+        let profiles = self.fetch_profiles()?;
+        *cached = Some(profiles.clone());
         Ok(profiles)
     }
 
-    /// Switch to an existing profile by tailnet name
-    pub fn switch_to(&self, tailnet_name: &str) -> Result<()> {
-        let mut cmd = self.create_command();
-        cmd.arg("switch");
-        cmd.arg(tailnet_name);
+    /// Forget the cached profile list so the next `list_profiles` re-queries tailscale
+    pub fn invalidate_profiles(&self) {
+        *self.profiles.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
 
-        let status = cmd
-            .spawn()
-            .context("Failed to execute tailscale switch")?
-            .wait()
-            .context("Failed to wait for tailscale switch")?;
+    fn fetch_profiles(&self) -> Result<Vec<Profile>> {
+        if let Some(mut profiles) = self
+            .local_api_get("profiles/")
+            .and_then(|json| parse_profiles_json(&json).ok())
+        {
+            let current = self
+                .local_api_get("profiles/current")
+                .and_then(|json| serde_json::from_str::<RawProfile>(&json).ok());
+            if let Some(current) = current {
+                for profile in &mut profiles {
+                    profile.active = profile.id == current.id;
+                }
+            }
+            return Ok(profiles);
+        }
+
+        let output = self.run_read(&["switch", "--list"])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to list profiles: {}", stderr.trim());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        // Format: ID  TAILNET  ACCOUNT (account may contain spaces or be missing)
+        Ok(parse_columns(&stdout)
+            .into_iter()
+            .filter_map(|row| {
+                let id = row.first().filter(|id| !id.is_empty())?.clone();
+                let tailnet = row.get(1).filter(|name| !name.is_empty())?.clone();
+                // The active profile is marked with a trailing *
+                let account = row.get(2).map(String::as_str).unwrap_or_default();
+                let active = account.ends_with('*');
+                Some(Profile {
+                    id,
+                    tailnet,
+                    account: account.trim_end_matches('*').trim_end().to_string(),
+                    active,
+                })
+            })
+            .collect())
+    }
+
+    /// Switch to an existing profile by ID (or tailnet name, which is
+    /// ambiguous when several accounts share a tailnet)
+    pub fn switch_to(&self, profile: &str) -> Result<()> {
+        let args = ["switch", profile];
+        if self.skip_for_dry_run(&args) {
+            return Ok(());
+        }
+
+        let status = self.runner.run_interactive(&args)?;
 
         if !status.success() {
-            anyhow::bail!("Failed to switch to {}", tailnet_name);
+            anyhow::bail!("Failed to switch to {}", profile);
         }
 
         Ok(())
     }
 
     /// Login to a tailnet and return the authentication URL if one is needed
-    pub fn login_and_get_url(&self, tailnet: &Tailnet) -> Result<Option<String>> {
+    pub fn login_and_get_url(&self, tailnet: &Tailnet) -> Result<Option<PendingLogin>> {
+        self.start_auth(tailnet, false)
+    }
+
+    /// Re-authenticate the current profile with `tailscale up --force-reauth`
+    /// (e.g. before its key expires), returning the auth URL if one is needed
+    pub fn force_reauth_and_get_url(&self, tailnet: &Tailnet) -> Result<Option<PendingLogin>> {
+        self.start_auth(tailnet, true)
+    }
+
+    fn start_auth(&self, tailnet: &Tailnet, force_reauth: bool) -> Result<Option<PendingLogin>> {
+        // Logging in may add a profile
+        self.invalidate_profiles();
+
         // With auth key, just run normally and wait
-        if let Some(ref auth_key) = tailnet.auth_key {
-            let mut cmd = self.create_command();
-            cmd.arg("up");
+        if let Some(ref auth_key) = self.auth_key(tailnet)? {
+            let mut args = vec!["up"];
+            if force_reauth {
+                args.push("--force-reauth");
+            }
 
             if let Some(ref server) = tailnet.login_server {
-                cmd.arg("--login-server").arg(server);
+                args.extend(["--login-server", server]);
             }
-            cmd.arg("--auth-key").arg(auth_key);
+            args.extend(["--auth-key", auth_key]);
 
             // Add custom flags if specified
             if let Some(ref flags) = tailnet.flags {
-                for flag in flags {
-                    cmd.arg(flag);
-                }
+                args.extend(flags.iter().map(String::as_str));
             }
 
-            let status = cmd
-                .spawn()
-                .context("Failed to execute tailscale up")?
-                .wait()
-                .context("Failed to wait for tailscale up")?;
-
-            if !status.success() {
-                anyhow::bail!("Tailscale login failed with exit code: {:?}", status.code());
-            }
+            self.run_interactive(&args, "Tailscale login")?;
             return Ok(None);
         }
 
         // For interactive auth: use 'tailscale login' which always requires auth
         // Unlike 'tailscale up', login always opens a new auth flow
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let log_file = format!("/tmp/tailscale-auth-{}.log", timestamp);
-
-        // Build the command - use 'login' not 'up'
-        let mut cmd_args = vec!["login".to_string()];
+        // Unique, owner-only log file that is removed once we're done reading it.
+        // The background process keeps its own handle, so removal is safe.
+        let log_file = tempfile::Builder::new()
+            .prefix("tailscale-auth-")
+            .suffix(".log")
+            .tempfile()
+            .context("Failed to create auth log file")?;
+
+        // Build the command - use 'login' not 'up', unless re-authenticating
+        // the current profile in place
+        let mut args = if force_reauth {
+            vec!["up", "--force-reauth"]
+        } else {
+            vec!["login"]
+        };
         if let Some(ref server) = tailnet.login_server {
-            cmd_args.push("--login-server".to_string());
-            cmd_args.push(server.clone());
+            args.extend(["--login-server", server]);
         }
 
         // Add custom flags if specified
         if let Some(ref flags) = tailnet.flags {
-            for flag in flags {
-                cmd_args.push(flag.clone());
-            }
+            args.extend(flags.iter().map(String::as_str));
         }
 
-        let script = if self.use_sudo {
-            format!(
-                "sudo tailscale {} > {} 2>&1 &",
-                cmd_args.join(" "),
-                log_file
-            )
-        } else {
-            format!("tailscale {} > {} 2>&1 &", cmd_args.join(" "), log_file)
-        };
+        if self.skip_for_dry_run(&args) {
+            return Ok(None);
+        }
 
         // Start tailscale in background
-        Command::new("bash")
-            .arg("-c")
-            .arg(&script)
-            .spawn()
-            .context("Failed to start tailscale login")?;
+        let pid = self.runner.spawn_logged(&args, log_file.path())?;
 
         // Wait for the URL to appear in the log file
-        for _ in 0..50 {
-            std::thread::sleep(std::time::Duration::from_millis(200));
-
-            if let Ok(contents) = std::fs::read_to_string(&log_file) {
-                // Look for the URL in the output
-                for line in contents.lines() {
-                    if let Some(start) = line.find("https://login.tailscale.com") {
-                        let url_part = &line[start..];
-                        // Extract just the URL (stop at whitespace)
-                        let url = url_part.split_whitespace().next().unwrap_or("").to_string();
-
-                        if !url.is_empty() {
-                            return Ok(Some(url));
-                        }
-                    }
-                }
+        let url = wait_for_auth_url(log_file.path(), tailnet.login_server.as_deref());
+
+        // No URL within the timeout: don't leave the login running untracked
+        let Some(url) = url else {
+            if let Err(e) = self.runner.kill(pid) {
+                log::warn!("Failed to stop tailscale login (PID {}): {:#}", pid, e);
             }
-        }
+            return Ok(None);
+        };
+        Ok(Some(PendingLogin { url, pid }))
+    }
 
-        // No URL found within timeout
-        Ok(None)
+    /// Stop a background login started by `login_and_get_url`
+    pub fn cancel_login(&self, login: &PendingLogin) -> Result<()> {
+        self.runner
+            .kill(login.pid)
+            .with_context(|| format!("Failed to stop tailscale login (PID {})", login.pid))
     }
 
     /// Get current tailscale status
     pub fn status(&self) -> Result<String> {
-        let mut cmd = self.create_command();
-        let output = cmd
-            .arg("status")
-            .output()
-            .context("Failed to execute tailscale status")?;
+        let output = self.run_read(&["status"])?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -208,70 +434,675 @@ impl TailscaleClient {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
-    /// Check if currently logged out
-    pub fn is_logged_out(&self) -> Result<bool> {
-        let mut cmd = self.create_command();
-        let output = cmd
-            .arg("status")
-            .output()
-            .context("Failed to execute tailscale status")?;
+    /// Start `tailscale ping` to a peer (IP or hostname) in the background.
+    /// It never goes through sudo: ping doesn't need root, and a password
+    /// prompt would garble the TUI. It's killed after the command timeout.
+    pub fn ping(&self, target: &str) -> Result<Streaming> {
+        self.runner
+            .with_sudo(false)
+            .spawn_streaming(&["ping", target])
+    }
+
+    /// Run `tailscale netcheck` and return its text report
+    pub fn netcheck(&self) -> Result<String> {
+        let output = self.run_read(&["netcheck"])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Tailscale netcheck failed: {}", stderr.trim());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Installed tailscale version, e.g. "1.76.1" (first line of `tailscale version`)
+    pub fn version(&self) -> Result<String> {
+        let output = self.run_read(&["version"])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Tailscale version failed: {}", stderr.trim());
+        }
 
         let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        let version = stdout.lines().next().unwrap_or_default().trim();
+        if version.is_empty() {
+            anyhow::bail!("Tailscale version printed nothing");
+        }
+        Ok(version.to_string())
+    }
+
+    /// Get current tailscale status as structured data
+    pub fn status_json(&self) -> Result<StatusInfo> {
+        if let Some(status) = self
+            .local_api_get("status")
+            .and_then(|json| StatusInfo::from_json(&json).ok())
+        {
+            return Ok(status);
+        }
+
+        let output = self.run_read(&["status", "--json"])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Tailscale status failed: {}", stderr);
+        }
+
+        StatusInfo::from_json(&String::from_utf8_lossy(&output.stdout))
+    }
 
-        // Check both stdout and stderr for logout indicators
-        // "Logged out." appears in stdout
-        // "Log in at:" appears in stderr
-        Ok(stdout.contains("Logged out") || stderr.contains("Log in at:"))
+    /// Poll status until the node is connected or needs to log in, for at
+    /// most `timeout`, and return the last status seen
+    pub fn wait_until_connected(&self, timeout: Duration) -> Result<StatusInfo> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = self.status_json()?;
+            if status.is_connected() || status.is_logged_out() || Instant::now() >= deadline {
+                return Ok(status);
+            }
+            std::thread::sleep(CONNECT_POLL_INTERVAL);
+        }
+    }
+
+    /// List exit nodes available in the current tailnet
+    pub fn exit_node_list(&self) -> Result<Vec<ExitNode>> {
+        let output = self.run_read(&["exit-node", "list"])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to list exit nodes: {}", stderr.trim());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        // Format: IP  HOSTNAME  COUNTRY  CITY  STATUS (city may contain spaces)
+        Ok(parse_columns(&stdout)
+            .into_iter()
+            .filter(|row| !row[0].is_empty())
+            .map(|row| {
+                let field = |i: usize| row.get(i).cloned().unwrap_or_default();
+                ExitNode {
+                    ip: field(0),
+                    hostname: field(1),
+                    country: field(2),
+                    city: field(3),
+                    status: field(4),
+                }
+            })
+            .collect())
+    }
+
+    /// Use the given exit node (IP or name), or stop using one with `None`
+    pub fn set_exit_node(&self, node: Option<&str>) -> Result<()> {
+        self.set(&format!("--exit-node={}", node.unwrap_or("")))
+    }
+
+    /// Get the current daemon preferences
+    pub fn prefs(&self) -> Result<Prefs> {
+        if let Some(prefs) = self
+            .local_api_get("prefs")
+            .and_then(|json| serde_json::from_str(&json).ok())
+        {
+            return Ok(prefs);
+        }
+
+        let output = self.run_read(&["debug", "prefs"])?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to read tailscale prefs: {}", stderr.trim());
+        }
+
+        serde_json::from_slice(&output.stdout).context("Failed to parse tailscale prefs")
+    }
+
+    /// Change a single preference with `tailscale set`, e.g. `--accept-routes=true`
+    pub fn set(&self, flag: &str) -> Result<()> {
+        self.run_interactive(&["set", flag], "Tailscale set")
     }
 
     /// Check if we need sudo
-    /// On most systems, tailscale operations require root regardless of operator setting
-    pub fn check_needs_sudo() -> bool {
+    ///
+    /// Root never does. Otherwise probe the daemon without sudo: tailscale
+    /// accepts commands from the configured operator (`tailscale set --operator`),
+    /// so sudo is only needed when we can't read the prefs or aren't the operator.
+    pub fn check_needs_sudo(binary: &str) -> bool {
+        // The Windows service runs elevated and serves local users directly, and
+        // the macOS app's system extension does the same for the logged-in user
+        if cfg!(any(windows, target_os = "macos")) {
+            return false;
+        }
+
         // Check if we're already running as root or with sudo
-        std::env::var("USER").unwrap_or_default() != "root" && std::env::var("SUDO_USER").is_err()
+        let user = std::env::var("USER").unwrap_or_default();
+        if user == "root" || std::env::var("SUDO_USER").is_ok() {
+            return false;
+        }
+
+        let probe = Command::new(binary)
+            .args(["debug", "prefs"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(anyhow::Error::from)
+            .and_then(|child| runner::output_with_timeout(child, Some(runner::DEFAULT_TIMEOUT)));
+        let output = match probe {
+            Ok(output) if output.status.success() => output,
+            // Permission denied (or an old tailscale without the command)
+            _ => return true,
+        };
+
+        match serde_json::from_slice::<Prefs>(&output.stdout) {
+            Ok(prefs) => prefs.operator_user.is_empty() || prefs.operator_user != user,
+            Err(_) => true,
+        }
+    }
+
+    /// Whether tailscaled answers at all. Status is readable without root, so
+    /// this works before deciding on sudo.
+    pub fn check_daemon_running(binary: &str) -> bool {
+        let probe = Command::new(binary)
+            .args(["status", "--json"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(anyhow::Error::from)
+            .and_then(|child| runner::output_with_timeout(child, Some(runner::DEFAULT_TIMEOUT)));
+
+        match probe {
+            Ok(output) => {
+                output.status.success() || !is_daemon_down(&String::from_utf8_lossy(&output.stderr))
+            }
+            // Anything else is left for the real commands to report
+            Err(_) => true,
+        }
     }
 
     /// Check if tailscale is installed
-    pub fn check_installed() -> Result<bool> {
-        let output = Command::new("which")
-            .arg("tailscale")
-            .output()
-            .context("Failed to check if tailscale is installed")?;
+    pub fn check_installed(binary: &str) -> Result<bool> {
+        // Explicit paths are checked directly, bare names are looked up on PATH
+        let path = Path::new(binary);
+        if path.components().count() > 1 {
+            return Ok(path.is_file());
+        }
 
-        Ok(output.status.success())
+        Ok(find_on_path(binary).is_some())
     }
 
     /// Run tailscale up with configured flags
     pub fn run_up(&self, tailnet: &Tailnet) -> Result<()> {
-        let mut cmd = self.create_command();
-        cmd.arg("up");
+        let mut args = vec!["up"];
 
         if let Some(ref server) = tailnet.login_server {
-            cmd.arg("--login-server").arg(server);
+            args.extend(["--login-server", server]);
         }
 
-        if let Some(ref auth_key) = tailnet.auth_key {
-            cmd.arg("--auth-key").arg(auth_key);
+        let auth_key = self.auth_key(tailnet)?;
+        if let Some(ref auth_key) = auth_key {
+            args.extend(["--auth-key", auth_key]);
         }
 
         // Add custom flags if specified
         if let Some(ref flags) = tailnet.flags {
-            for flag in flags {
-                cmd.arg(flag);
+            args.extend(flags.iter().map(String::as_str));
+        }
+
+        self.run_interactive(&args, "Tailscale up")
+    }
+}
+
+// LocalAPI profile shape - only the fields `switch --list` shows
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawProfile {
+    #[serde(rename = "ID", default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    network_profile: RawNetworkProfile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawNetworkProfile {
+    #[serde(default)]
+    domain_name: String,
+    #[serde(default)]
+    display_name: String,
+}
+
+/// The tailscale binary to use when none is configured: `tailscale` from
+/// PATH, or where the installer puts it if that's not on PATH
+pub fn default_binary() -> String {
+    const NAME: &str = "tailscale";
+    if find_on_path(NAME).is_some() {
+        return NAME.to_string();
+    }
+
+    // The Windows installer doesn't always add itself to PATH, and the macOS
+    // app (App Store or standalone) only adds a CLI when asked to
+    let installed = if cfg!(windows) {
+        std::env::var_os("ProgramFiles")
+            .map(|dir| PathBuf::from(dir).join("Tailscale").join("tailscale.exe"))
+    } else if cfg!(target_os = "macos") {
+        Some(PathBuf::from(MACOS_APP_BINARY))
+    } else {
+        None
+    };
+
+    installed
+        .filter(|path| path.is_file())
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| NAME.to_string())
+}
+
+/// Look a bare command name up on PATH (trying `.exe` on Windows)
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let names: Vec<String> = if cfg!(windows) {
+        vec![format!("{}.exe", name), name.to_string()]
+    } else {
+        vec![name.to_string()]
+    };
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Whether tailscale's error output means tailscaled itself isn't running,
+/// as opposed to a command failing
+pub fn is_daemon_down(stderr: &str) -> bool {
+    const HINTS: [&str; 4] = [
+        "failed to connect to local tailscale",
+        "doesn't appear to be running",
+        "is tailscale running",
+        "tailscaled.sock: connect",
+    ];
+    let stderr = stderr.to_lowercase();
+    HINTS.iter().any(|hint| stderr.contains(hint))
+}
+
+/// Failures worth retrying: the daemon isn't reachable right now
+fn is_transient_failure(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    is_daemon_down(&stderr) || stderr.to_lowercase().contains("connection refused")
+}
+
+/// The usual way to start tailscaled on this platform, if there is one
+pub fn daemon_start_command() -> Option<Vec<&'static str>> {
+    let root = std::env::var("USER").is_ok_and(|user| user == "root");
+    let sudo = if root { None } else { Some("sudo") };
+
+    let command = if cfg!(target_os = "macos") {
+        vec!["open", "-a", "Tailscale"]
+    } else if cfg!(windows) {
+        vec!["net", "start", "Tailscale"]
+    } else if cfg!(target_os = "linux") {
+        sudo.into_iter()
+            .chain(["systemctl", "start", "tailscaled"])
+            .collect()
+    } else if cfg!(target_os = "freebsd") {
+        sudo.into_iter()
+            .chain(["service", "tailscaled", "start"])
+            .collect()
+    } else {
+        return None;
+    };
+    Some(command)
+}
+
+/// Parse the LocalAPI profile list, naming tailnets the same way
+/// `tailscale switch --list` does. `active` is left unset.
+fn parse_profiles_json(json: &str) -> Result<Vec<Profile>> {
+    let profiles: Option<Vec<RawProfile>> =
+        serde_json::from_str(json).context("Failed to parse LocalAPI profiles")?;
+
+    Ok(profiles
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|profile| {
+            let network = profile.network_profile;
+            let tailnet = if network.display_name.is_empty() {
+                network.domain_name
+            } else {
+                network.display_name
+            };
+            (!tailnet.is_empty() && !profile.id.is_empty()).then_some(Profile {
+                id: profile.id,
+                tailnet,
+                account: profile.name,
+                active: false,
+            })
+        })
+        .collect())
+}
+
+/// Watch the login log until an auth URL shows up or `LOGIN_URL_TIMEOUT`
+/// passes. File change notifications make the URL show up as soon as it's
+/// written; without them (unsupported filesystem, watch limit) we poll.
+fn wait_for_auth_url(log_path: &Path, login_server: Option<&str>) -> Option<String> {
+    let deadline = Instant::now() + LOGIN_URL_TIMEOUT;
+
+    let (sender, receiver) = mpsc::channel();
+    let watcher = notify::recommended_watcher(move |_: notify::Result<notify::Event>| {
+        let _ = sender.send(());
+    })
+    .and_then(|mut watcher| {
+        watcher
+            .watch(log_path, RecursiveMode::NonRecursive)
+            .map(|()| watcher)
+    });
+
+    loop {
+        if let Ok(contents) = std::fs::read_to_string(log_path)
+            && let Some(url) = extract_auth_url(&contents, login_server)
+        {
+            return Some(url);
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+
+        match watcher {
+            // Re-check at least every second in case an event gets lost
+            Ok(_) => {
+                let _ = receiver.recv_timeout(remaining.min(Duration::from_secs(1)));
             }
+            Err(_) => std::thread::sleep(remaining.min(LOGIN_POLL_INTERVAL)),
         }
+    }
+}
 
-        let status = cmd
-            .spawn()
-            .context("Failed to execute tailscale up")?
-            .wait()
-            .context("Failed to wait for tailscale up")?;
+/// Find the authentication URL in `tailscale login` output.
+///
+/// Scans the whole log (stdout and stderr) rather than line by line, since
+/// some versions print the URL on the line after "To authenticate, visit:".
+/// A URL directly following that prompt is always accepted; otherwise the URL
+/// must point at the tailnet's control server host.
+fn extract_auth_url(output: &str, login_server: Option<&str>) -> Option<String> {
+    let re = Regex::new(r"(To authenticate, visit:\s*)?(https://\S+)").expect("valid regex");
+    let expected_host = login_server.map(url_host).unwrap_or(DEFAULT_CONTROL_HOST);
 
-        if !status.success() {
-            anyhow::bail!("Tailscale up failed with exit code: {:?}", status.code());
+    re.captures_iter(output).find_map(|caps| {
+        let url = caps.get(2)?.as_str();
+        if caps.get(1).is_some() || url_host(url).eq_ignore_ascii_case(expected_host) {
+            Some(url.to_string())
+        } else {
+            None
         }
+    })
+}
 
-        Ok(())
+/// Split tabular CLI output into trimmed fields using the header's column
+/// positions, so values containing spaces stay intact. Blank lines and
+/// `#` comment lines are skipped; the header itself is not returned.
+fn parse_columns(output: &str) -> Vec<Vec<String>> {
+    let mut lines = output
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+
+    // Column starts are where a header word begins after whitespace
+    let header: Vec<char> = header.chars().collect();
+    let starts: Vec<usize> = (0..header.len())
+        .filter(|&i| !header[i].is_whitespace() && (i == 0 || header[i - 1].is_whitespace()))
+        .collect();
+
+    lines
+        .map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            starts
+                .iter()
+                .enumerate()
+                .map(|(n, &start)| {
+                    // The last column takes the rest of the line
+                    let end = starts.get(n + 1).copied().unwrap_or(chars.len());
+                    let start = start.min(chars.len());
+                    let end = end.clamp(start, chars.len());
+                    chars[start..end]
+                        .iter()
+                        .collect::<String>()
+                        .trim()
+                        .to_string()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Extract the host portion of a URL (without scheme, port or path)
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    rest.split(['/', ':', '?', '#']).next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::Streaming;
+    use std::process::{ExitStatus, Output};
+
+    /// Answers every command with the same canned stdout and exit code
+    struct MockRunner {
+        stdout: String,
+        code: i32,
+    }
+
+    impl MockRunner {
+        fn client(stdout: &str) -> TailscaleClient {
+            TailscaleClient::with_runner(Box::new(Self {
+                stdout: stdout.to_string(),
+                code: 0,
+            }))
+        }
+    }
+
+    fn exit_status(code: i32) -> ExitStatus {
+        #[cfg(unix)]
+        {
+            std::os::unix::process::ExitStatusExt::from_raw(code << 8)
+        }
+        #[cfg(windows)]
+        {
+            std::os::windows::process::ExitStatusExt::from_raw(code as u32)
+        }
+    }
+
+    impl CommandRunner for MockRunner {
+        fn run(&self, _args: &[&str]) -> Result<Output> {
+            Ok(Output {
+                status: exit_status(self.code),
+                stdout: self.stdout.clone().into_bytes(),
+                stderr: Vec::new(),
+            })
+        }
+
+        fn run_interactive(&self, _args: &[&str]) -> Result<ExitStatus> {
+            Ok(exit_status(self.code))
+        }
+
+        fn spawn_logged(&self, _args: &[&str], _log_path: &Path) -> Result<u32> {
+            anyhow::bail!("not supported by the mock")
+        }
+
+        fn kill(&self, _pid: u32) -> Result<()> {
+            anyhow::bail!("not supported by the mock")
+        }
+
+        fn spawn_streaming(&self, _args: &[&str]) -> Result<Streaming> {
+            anyhow::bail!("not supported by the mock")
+        }
+
+        fn with_sudo(&self, _use_sudo: bool) -> Box<dyn CommandRunner> {
+            Box::new(Self {
+                stdout: self.stdout.clone(),
+                code: self.code,
+            })
+        }
+    }
+
+    #[test]
+    fn dry_run_leaves_the_auth_key_source_alone() {
+        let client = MockRunner::client("").with_dry_run(true);
+        let tailnet = Tailnet {
+            name: "work".to_string(),
+            auth_key_command: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        assert!(client.login_and_get_url(&tailnet).unwrap().is_none());
+
+        let tailnet = Tailnet {
+            auth_key_command: None,
+            auth_key_file: Some("/nonexistent/tailswitch-key".to_string()),
+            ..tailnet
+        };
+        client.run_up(&tailnet).unwrap();
+
+        assert_eq!(
+            client.take_dry_run_commands(),
+            [
+                "tailscale up --auth-key ****",
+                "tailscale up --auth-key ****"
+            ]
+        );
+    }
+
+    #[test]
+    fn list_profiles_marks_the_active_profile() {
+        let client = MockRunner::client(
+            "ID    Tailnet       Account\n\
+             1a2b  corp.ts.net   alice@corp.com*\n\
+             3c4d  corp.ts.net   bob@corp.com\n",
+        );
+        let profiles = client.list_profiles().unwrap();
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].id, "1a2b");
+        assert_eq!(profiles[0].tailnet, "corp.ts.net");
+        assert_eq!(profiles[0].account, "alice@corp.com");
+        assert!(profiles[0].active);
+        assert_eq!(profiles[1].account, "bob@corp.com");
+        assert!(!profiles[1].active);
+    }
+
+    #[test]
+    fn list_profiles_handles_a_missing_account() {
+        let client = MockRunner::client(
+            "ID    Tailnet       Account\n\
+             1a2b  corp.ts.net   alice@corp.com\n\
+             5e6f  home.ts.net\n\
+             7a8b  lab.ts.net    *\n",
+        );
+        let profiles = client.list_profiles().unwrap();
+
+        assert_eq!(profiles.len(), 3);
+        assert_eq!(profiles[1].tailnet, "home.ts.net");
+        assert_eq!(profiles[1].account, "");
+        assert!(!profiles[1].active);
+        // Only the active marker is left where the account would be
+        assert_eq!(profiles[2].account, "");
+        assert!(profiles[2].active);
+    }
+
+    #[test]
+    fn list_profiles_fails_when_tailscale_does() {
+        let client = TailscaleClient::with_runner(Box::new(MockRunner {
+            stdout: String::new(),
+            code: 1,
+        }));
+        assert!(client.list_profiles().is_err());
+    }
+
+    #[test]
+    fn parse_columns_keeps_multi_word_accounts() {
+        let rows = parse_columns(
+            "ID    Tailnet       Account\n\
+             1a2b  corp.ts.net   Alice Example (work)*\n",
+        );
+        assert_eq!(
+            rows,
+            vec![vec!["1a2b", "corp.ts.net", "Alice Example (work)*"]]
+        );
+    }
+
+    #[test]
+    fn parse_columns_pads_rows_without_an_account() {
+        let rows = parse_columns(
+            "ID    Tailnet       Account\n\
+             \n\
+             5e6f  home.ts.net\n",
+        );
+        assert_eq!(rows, vec![vec!["5e6f", "home.ts.net", ""]]);
+    }
+
+    #[test]
+    fn extract_auth_url_finds_a_headscale_url() {
+        let log = "Logging in...\n\
+                   To authenticate, visit: https://headscale.example.com/register/mkey:abc123\n";
+        assert_eq!(
+            extract_auth_url(log, Some("https://headscale.example.com")),
+            Some("https://headscale.example.com/register/mkey:abc123".to_string())
+        );
+    }
+
+    #[test]
+    fn extract_auth_url_matches_the_login_server_host() {
+        // No prompt before it, so the host has to match the login server
+        let log = "Visit https://headscale.example.com:8080/register/mkey:abc123 to log in\n";
+        assert_eq!(
+            extract_auth_url(log, Some("https://headscale.example.com:8080")),
+            Some("https://headscale.example.com:8080/register/mkey:abc123".to_string())
+        );
+        assert_eq!(extract_auth_url(log, None), None);
+    }
+
+    #[test]
+    fn extract_auth_url_reads_the_url_on_the_next_line() {
+        let log = "Release notes: https://tailscale.com/changelog\n\
+                   \n\
+                   To authenticate, visit:\n\
+                   \n\
+                   \thttps://login.example.net/a/abc123\n\
+                   \n\
+                   Docs: https://tailscale.com/kb/1080/cli\n";
+        assert_eq!(
+            extract_auth_url(log, Some("https://login.example.net")),
+            Some("https://login.example.net/a/abc123".to_string())
+        );
+        // The prompt alone is enough, whatever the host
+        assert_eq!(
+            extract_auth_url(log, None),
+            Some("https://login.example.net/a/abc123".to_string())
+        );
+    }
+
+    #[test]
+    fn list_profiles_parses_macos_app_output() {
+        // `/Applications/Tailscale.app/Contents/MacOS/Tailscale switch --list`,
+        // laid out by the CLI's tabwriter (two spaces of padding); personal
+        // tailnets are named after the account
+        let client = MockRunner::client(
+            "ID    Tailnet          Account\n\
+             a7c3  example.com      alice@example.com*\n\
+             f2d9  alice@gmail.com  alice@gmail.com\n",
+        );
+        let profiles = client.list_profiles().unwrap();
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].id, "a7c3");
+        assert_eq!(profiles[0].tailnet, "example.com");
+        assert_eq!(profiles[0].account, "alice@example.com");
+        assert!(profiles[0].active);
+        assert_eq!(profiles[1].tailnet, "alice@gmail.com");
+        assert_eq!(profiles[1].account, "alice@gmail.com");
+        assert!(!profiles[1].active);
     }
 }
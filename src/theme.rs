@@ -0,0 +1,113 @@
+use ratatui::style::{Color, Modifier, Style};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Built-in themes, selectable with `theme` in the config
+pub const THEME_NAMES: [&str; 3] = ["default", "mono", "solarized"];
+
+/// Set once at startup from `NO_COLOR` / `--no-color`
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_color(no_color: bool) {
+    NO_COLOR.store(no_color, Ordering::Relaxed);
+}
+
+/// Whether `NO_COLOR` / `--no-color` turned colors off
+pub fn no_color() -> bool {
+    NO_COLOR.load(Ordering::Relaxed)
+}
+
+/// Colors the TUI draws with, by role
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Screen titles
+    pub header: Color,
+    /// Highlighted row in lists and tables
+    pub selection: Style,
+    /// The connected tailnet, online peers and successes
+    pub active: Color,
+    /// Key hints, borders, headings and focused fields
+    pub accent: Color,
+    /// The active ★, logged-out ⚠ and new-entry `+` markers, the peers table
+    /// header, and notices such as an available update or expiring key
+    pub warning: Color,
+    /// Failures and destructive keys
+    pub error: Color,
+    /// Regular text
+    pub text: Color,
+    /// Secondary text such as accounts and key hint separators
+    pub muted: Color,
+    /// Least important text: labels and offline peers
+    pub dim: Color,
+    /// The QR code, which needs strong contrast to scan
+    pub qr: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            header: Color::Cyan,
+            selection: Style::default().bg(Color::Blue),
+            active: Color::Green,
+            accent: Color::Cyan,
+            warning: Color::Yellow,
+            error: Color::Red,
+            text: Color::White,
+            muted: Color::Gray,
+            dim: Color::DarkGray,
+            qr: Style::default().fg(Color::White).bg(Color::Black),
+        }
+    }
+}
+
+impl Theme {
+    /// Look up a built-in theme by name
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "mono" => Some(Self::mono()),
+            "solarized" => Some(Self::solarized()),
+            _ => None,
+        }
+    }
+
+    /// The named theme (default if unset or unknown), or mono when colors
+    /// are disabled
+    pub fn resolve(name: Option<&str>) -> Self {
+        if no_color() {
+            return Self::mono();
+        }
+        name.and_then(Self::named).unwrap_or_default()
+    }
+
+    /// The terminal's own colors only; selections are shown reversed
+    fn mono() -> Self {
+        Self {
+            header: Color::Reset,
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            active: Color::Reset,
+            accent: Color::Reset,
+            warning: Color::Reset,
+            error: Color::Reset,
+            text: Color::Reset,
+            muted: Color::Reset,
+            dim: Color::Reset,
+            qr: Style::default(),
+        }
+    }
+
+    /// Ethan Schoonover's Solarized palette, for dark terminals
+    fn solarized() -> Self {
+        Self {
+            header: Color::Rgb(0x26, 0x8b, 0xd2),
+            selection: Style::default().bg(Color::Rgb(0x07, 0x36, 0x42)),
+            active: Color::Rgb(0x85, 0x99, 0x00),
+            accent: Color::Rgb(0x2a, 0xa1, 0x98),
+            warning: Color::Rgb(0xb5, 0x89, 0x00),
+            error: Color::Rgb(0xdc, 0x32, 0x2f),
+            text: Color::Rgb(0x93, 0xa1, 0xa1),
+            muted: Color::Rgb(0x83, 0x94, 0x96),
+            dim: Color::Rgb(0x58, 0x6e, 0x75),
+            qr: Style::default().fg(Color::White).bg(Color::Black),
+        }
+    }
+}
@@ -1,78 +1,529 @@
 use crate::config::{Config, Tailnet};
-use anyhow::Result;
+use crate::datetime;
+use crate::options::{self, TailnetOption};
+use crate::runner::{self, Streaming};
+use crate::status::{ExitNodeStatus, PeerConnection, PeerStatus, StatusInfo};
+use crate::tailscale::{ExitNode, Prefs};
+use crate::theme::Theme;
+use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{
+        Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Table, TableState,
+    },
 };
+use std::borrow::Cow;
 use std::io;
+use std::net::IpAddr;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// How long to wait for input before redrawing
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Narrowest list area that still gets the status panel beside it
+const TWO_PANE_MIN_WIDTH: u16 = 100;
+
+/// Most health warnings shown at once, so the list keeps its room
+const MAX_HEALTH_LINES: usize = 4;
+
+/// Columns a long tailnet or account name keeps when squeezed by its row
+const MIN_NAME_WIDTH: usize = 12;
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_TICK: Duration = Duration::from_millis(80);
+
+/// Run `work` on a background thread while showing an animated spinner with
+/// `message`, so slow tailscale/sudo calls don't look like a frozen terminal.
+/// `q`, `Esc` or Ctrl-C abort tailswitch.
+pub fn with_spinner<T: Send>(
+    theme: Theme,
+    message: &str,
+    work: impl FnOnce() -> T + Send,
+) -> Result<T> {
+    let _guard = TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    std::thread::scope(|scope| {
+        let handle = scope.spawn(work);
+
+        let mut tick = 0;
+        while !handle.is_finished() {
+            terminal.draw(|f| {
+                render_spinner(f, &theme, message, tick);
+            })?;
+            tick += 1;
+
+            if event::poll(SPINNER_TICK)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    restore_terminal();
+                    std::process::exit(130);
+                }
+            }
+        }
+
+        Ok(handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+    })
+}
+
+fn render_spinner(f: &mut Frame, theme: &Theme, message: &str, tick: usize) {
+    let area = centered_rect(50, 3, f.area());
+    let spinner = SPINNER_FRAMES[tick % SPINNER_FRAMES.len()];
+
+    let text = Line::from(vec![
+        Span::styled(spinner, Style::default().fg(theme.accent)),
+        Span::raw(" "),
+        Span::styled(message, Style::default().fg(theme.text)),
+    ]);
+    let popup = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("TailSwitch"));
+    f.render_widget(popup, area);
+}
+
+/// Raw mode + alternate screen + mouse capture for the lifetime of the guard
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        // Constructed before the screen switch so a failure there still restores raw mode
+        let guard = Self;
+        discard_pending_input()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        Ok(guard)
+    }
+}
+
+/// Drop input typed while no screen was reading it, e.g. Enter or j/k mashed
+/// during a switch or login, so it can't trigger actions once the TUI is back
+fn discard_pending_input() -> Result<()> {
+    let mut discarded = 0;
+    while event::poll(Duration::ZERO)? {
+        event::read()?;
+        discarded += 1;
+    }
+    if discarded > 0 {
+        log::debug!("Discarded {} input events typed while busy", discarded);
+    }
+    Ok(())
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leave raw mode, the alternate screen and mouse capture. Safe to call when
+/// none of them is active.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        cursor::Show
+    );
+}
+
+/// Restore the terminal before the default panic message is printed, so a
+/// crash inside the TUI doesn't leave the shell without echo
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+}
 
 pub enum AppAction {
     SelectTailnet(Tailnet),
-    RunTailscaleUp,
+    /// `tailscale up` with the tailnet's resolved flags
+    RunTailscaleUp(Tailnet),
+    /// `tailscale up --force-reauth` for the active tailnet, with its flags
+    Reauthenticate(Tailnet),
     ShowStatus,
+    /// `tailscale ping` to a peer from the status table
+    Ping(PeerStatus),
+    Netcheck,
+    RefreshProfiles,
     Logout,
+    DeleteProfile(TailnetOption),
+    /// Rename a profile to the given name
+    RenameProfile(TailnetOption, String),
+    PickExitNode,
+    SetExitNode(Option<String>),
+    ToggleAcceptRoutes,
+    ToggleAcceptDns,
     Quit,
 }
 
+impl AppAction {
+    /// Whether running this action talks to tailscale
+    fn needs_tailscale(&self) -> bool {
+        !matches!(self, AppAction::RefreshProfiles | AppAction::Quit)
+    }
+}
+
 pub struct App {
-    options: Vec<(String, Option<String>, bool, bool)>, // (name, account, is_existing_profile, is_active)
+    options: Vec<TailnetOption>,
     list_state: ListState,
     should_quit: bool,
     status_message: Option<String>,
-    _config: Config,
+    config: Config,
     output_view: Option<OutputView>,
+    filter: Option<String>, // Some while in filter mode
+    visible: Vec<usize>,    // indices into options that match the filter
+    form: Option<TailnetForm>,
+    flags_form: Option<FlagsForm>,
+    confirmation: Option<Confirmation>,
+    rename: Option<RenamePrompt>,
+    exit_node_picker: Option<ExitNodePicker>,
+    peers_view: Option<PeersView>,
+    show_details: bool,       // config popup for the highlighted row
+    pending_g: bool,          // first half of `gg`
+    copied_ip: Option<usize>, // IP `y` just copied, so pressing it again moves on
+    show_help: bool,
+    list_height: usize, // rows available to the list on the last draw
+    list_area: Rect,    // where the list was last drawn, for mouse clicks
+    list_offset: usize, // first row drawn, counting group headers
+    prefs: Option<Prefs>,
+    status: Option<StatusInfo>, // cached so redraws don't query tailscale
+    status_updates: Option<Receiver<StatusInfo>>,
+    previous_tailnet: Option<String>,
+    version: Option<String>, // installed tailscale version, read once at startup
+    theme: Theme,
+    // Kept alive so the copied text stays available on X11/Wayland
+    clipboard: Option<arboard::Clipboard>,
+    ping: Option<(String, Streaming)>, // peer being pinged into the output view
+    offline: bool,                     // tailscale unreachable: only the config is shown, read-only
+}
+
+/// Exit node selection view. Row 0 is "None (disable exit node)".
+struct ExitNodePicker {
+    nodes: Vec<ExitNode>,
+    current: Option<ExitNodeStatus>,
+    list_state: ListState,
+}
+
+impl ExitNodePicker {
+    fn is_current(&self, node: &ExitNode) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|current| current.ips.contains(&node.ip))
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.nodes.len() + 1;
+        let i = self.list_state.selected().unwrap_or(0) as isize + delta;
+        self.list_state
+            .select(Some(i.rem_euclid(len as isize) as usize));
+    }
+}
+
+const PEER_COLUMNS: [&str; 5] = ["Hostname", "IP", "OS", "Status", "Connection"];
+
+/// Table of peers shown for `s`. The raw `tailscale status` text is kept for
+/// the `r` toggle.
+struct PeersView {
+    summary: String,
+    peers: Vec<PeerStatus>,
+    raw: String,
+    sort_column: usize, // index into PEER_COLUMNS
+    descending: bool,
+    table_state: TableState,
+}
+
+impl PeersView {
+    /// Sort by `column`, or flip the order if already sorted by it
+    fn sort_by(&mut self, column: usize) {
+        if self.sort_column == column {
+            self.descending = !self.descending;
+        } else {
+            self.sort_column = column;
+            self.descending = false;
+        }
+        self.apply_sort();
+    }
+
+    fn apply_sort(&mut self) {
+        let first_ip =
+            |peer: &PeerStatus| peer.ips.first().and_then(|ip| ip.parse::<IpAddr>().ok());
+        let by_name = |a: &PeerStatus, b: &PeerStatus| {
+            a.hostname.to_lowercase().cmp(&b.hostname.to_lowercase())
+        };
+
+        let column = self.sort_column;
+        self.peers.sort_by(|a, b| {
+            let ordering = match column {
+                1 => first_ip(a).cmp(&first_ip(b)),
+                2 => a.os.cmp(&b.os).then_with(|| by_name(a, b)),
+                // Online peers first
+                3 => b.online.cmp(&a.online).then_with(|| by_name(a, b)),
+                // Direct, then relayed, then peers without a connection
+                4 => connection_rank(a)
+                    .cmp(&connection_rank(b))
+                    .then_with(|| by_name(a, b)),
+                _ => by_name(a, b),
+            };
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.peers.is_empty() {
+            return;
+        }
+        let len = self.peers.len() as isize;
+        let i = self.table_state.selected().unwrap_or(0) as isize + delta;
+        self.table_state.select(Some(i.clamp(0, len - 1) as usize));
+    }
+}
+
+/// A yes/no prompt shown over the list before a destructive action
+struct Confirmation {
+    message: String,
+    on_confirm: ConfirmAction,
+}
+
+enum ConfirmAction {
+    SwitchTailnet(Tailnet),
+    Logout,
+    DeleteProfile(TailnetOption),
+    DeleteConfigEntry(String),
+}
+
+/// Text prompt for a profile's new name
+struct RenamePrompt {
+    option: TailnetOption,
+    name: String,
+}
+
+const FORM_LABELS: [&str; 4] = ["Name", "Login server", "Auth key", "Flags"];
+
+/// Input form for adding or editing a configured tailnet
+struct TailnetForm {
+    // Index into config.tailnets being edited, None when adding
+    editing: Option<usize>,
+    fields: [String; 4], // name, login_server, auth_key, shell-quoted flags
+    focus: usize,
+}
+
+impl TailnetForm {
+    fn from_tailnet(tailnet: &Tailnet, editing: Option<usize>) -> Self {
+        Self {
+            editing,
+            fields: [
+                tailnet.name.clone(),
+                tailnet.login_server.clone().unwrap_or_default(),
+                tailnet.auth_key.clone().unwrap_or_default(),
+                tailnet
+                    .flags
+                    .as_ref()
+                    .map(|flags| runner::shell_join(flags))
+                    .unwrap_or_default(),
+            ],
+            focus: 0,
+        }
+    }
+
+    /// Apply the form fields on top of an existing tailnet entry
+    fn apply_to(&self, tailnet: &mut Tailnet) {
+        let optional = |value: &str| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        let flags = runner::shell_split(&self.fields[3]);
+
+        tailnet.name = self.fields[0].trim().to_string();
+        tailnet.login_server = optional(&self.fields[1]);
+        tailnet.auth_key = optional(&self.fields[2]);
+        tailnet.flags = (!flags.is_empty()).then_some(flags);
+    }
+}
+
+/// Boolean `tailscale up` flags offered as checkboxes, with tailscale's default
+const FLAG_TOGGLES: [(&str, bool); 5] = [
+    ("--ssh", false),
+    ("--accept-routes", false),
+    ("--accept-dns", true),
+    ("--shields-up", false),
+    ("--advertise-exit-node", false),
+];
+
+/// Checkbox editor for a tailnet's flags. The row after the checkboxes is a
+/// free-text field for everything else.
+struct FlagsForm {
+    name: String,
+    checked: [bool; FLAG_TOGGLES.len()],
+    other: String,
+    focus: usize,
+}
+
+impl FlagsForm {
+    fn from_flags(name: String, flags: &[String]) -> Self {
+        let mut checked = FLAG_TOGGLES.map(|(_, default)| default);
+        let mut other = Vec::new();
+
+        for flag in flags {
+            let (key, value) = match flag.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (flag.as_str(), None),
+            };
+            let toggle = FLAG_TOGGLES.iter().position(|(name, _)| *name == key);
+            match toggle {
+                Some(index) if value.is_none_or(|v| v == "true" || v == "false") => {
+                    checked[index] = value != Some("false");
+                }
+                _ => other.push(flag.clone()),
+            }
+        }
+
+        Self {
+            name,
+            checked,
+            other: runner::shell_join(&other),
+            focus: 0,
+        }
+    }
+
+    /// Flags to store: only checkboxes that differ from tailscale's default,
+    /// followed by the free-text ones
+    fn flags(&self) -> Vec<String> {
+        let mut flags: Vec<String> = FLAG_TOGGLES
+            .iter()
+            .zip(self.checked)
+            .filter(|((_, default), checked)| checked != default)
+            .map(|((flag, _), checked)| {
+                if checked {
+                    flag.to_string()
+                } else {
+                    format!("{}=false", flag)
+                }
+            })
+            .collect();
+        flags.extend(runner::shell_split(&self.other));
+        flags
+    }
 }
 
 struct OutputView {
     title: String,
     content: String,
+    scroll: usize,
+    // Shown in the footer after saving with `w`
+    status_message: Option<(String, Color)>,
+    // Updated on each render so scrolling can be clamped to the content
+    total_lines: usize,
+    viewport_height: usize,
+}
+
+impl OutputView {
+    fn new(title: String, content: String) -> Self {
+        Self {
+            title,
+            content,
+            scroll: 0,
+            status_message: None,
+            total_lines: 0,
+            viewport_height: 0,
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.total_lines.saturating_sub(self.viewport_height)
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta)
+            .min(self.max_scroll());
+    }
+
+    fn page_size(&self) -> isize {
+        self.viewport_height.max(1) as isize
+    }
 }
 
 impl App {
-    pub fn new_with_options(
-        options: Vec<(String, Option<String>, bool, bool)>,
-        config: Config,
-    ) -> Self {
+    pub fn new_with_options(options: Vec<TailnetOption>, config: Config) -> Self {
         let mut list_state = ListState::default();
         if !options.is_empty() {
             list_state.select(Some(0));
         }
+        let visible = (0..options.len()).collect();
+        let theme = config.theme();
 
         Self {
             options,
             list_state,
             should_quit: false,
             status_message: None,
-            _config: config,
+            config,
             output_view: None,
+            filter: None,
+            visible,
+            form: None,
+            flags_form: None,
+            confirmation: None,
+            rename: None,
+            exit_node_picker: None,
+            peers_view: None,
+            show_details: false,
+            pending_g: false,
+            copied_ip: None,
+            show_help: false,
+            list_height: 0,
+            list_area: Rect::default(),
+            list_offset: 0,
+            prefs: None,
+            status: None,
+            status_updates: None,
+            previous_tailnet: None,
+            version: None,
+            theme,
+            clipboard: None,
+            ping: None,
+            offline: false,
         }
     }
 
     pub fn run(&mut self) -> Result<Option<AppAction>> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
-
-        let result = self.run_loop(&mut terminal);
+        // Setup terminal, restored when the guard drops (even on `?` or panic)
+        let _guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
 
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-        terminal.show_cursor()?;
-
-        result
+        self.run_loop(&mut terminal)
     }
 
     fn run_loop(
@@ -82,13 +533,50 @@ impl App {
         let mut action = None;
 
         loop {
-            terminal.draw(|f| self.ui(f))?;
+            // Pick up background status refreshes before drawing
+            if let Some(ref updates) = self.status_updates {
+                let latest = updates.try_iter().last();
+                if let Some(status) = latest {
+                    self.apply_status(status);
+                }
+            }
+
+            self.poll_ping();
 
-            if let Event::Key(key) = event::read()?
+            terminal.draw(|f| {
+                self.ui(f);
+            })?;
+
+            // Wake up regularly so refreshed status shows without a key press
+            let event = if event::poll(TICK_RATE)? {
+                Some(event::read()?)
+            } else {
+                None
+            };
+
+            if let Some(Event::Mouse(mouse)) = event
+                && let Some(clicked) = self.handle_mouse(mouse)
+            {
+                action = Some(clicked);
+                self.should_quit = true;
+            }
+
+            if let Some(Event::Key(key)) = event
                 && key.kind == KeyEventKind::Press
             {
-                // If we're in output view mode, handle differently
-                if self.output_view.is_some() {
+                if self.show_help {
+                    // Any key closes the help
+                    self.show_help = false;
+                } else if key.code == KeyCode::Char('?')
+                    && self.filter.is_none()
+                    && self.form.is_none()
+                    && self.flags_form.is_none()
+                    && self.confirmation.is_none()
+                    && self.rename.is_none()
+                {
+                    self.show_help = true;
+                } else if self.output_view.is_some() {
+                    // If we're in output view mode, handle differently
                     match key.code {
                         KeyCode::Esc | KeyCode::Enter => {
                             // Exit output view, go back to main menu
@@ -97,16 +585,103 @@ impl App {
                             action = None;
                             self.should_quit = false;
                         }
+                        KeyCode::Char('r') if self.peers_view.is_some() => {
+                            // Raw status toggled from the peers table
+                            self.output_view = None;
+                        }
                         KeyCode::Char('q') => {
                             // Exit output view and quit the app
                             self.output_view = None;
                             action = Some(AppAction::Quit);
                             self.should_quit = true;
                         }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            if let Some(ref mut output) = self.output_view {
+                                output.scroll_by(1);
+                            }
+                        }
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            if let Some(ref mut output) = self.output_view {
+                                output.scroll_by(-1);
+                            }
+                        }
+                        KeyCode::PageDown => {
+                            if let Some(ref mut output) = self.output_view {
+                                output.scroll_by(output.page_size());
+                            }
+                        }
+                        KeyCode::PageUp => {
+                            if let Some(ref mut output) = self.output_view {
+                                output.scroll_by(-output.page_size());
+                            }
+                        }
+                        KeyCode::Char('w') => self.save_output(),
+                        _ => {}
+                    }
+                } else if self.peers_view.is_some() {
+                    if let Some(quit) = self.handle_peers_key(key.code) {
+                        action = Some(quit);
+                        self.should_quit = true;
+                    }
+                } else if self.exit_node_picker.is_some() {
+                    if let Some(picked) = self.handle_exit_node_key(key.code) {
+                        action = Some(picked);
+                        self.should_quit = true;
+                    }
+                } else if self.confirmation.is_some() {
+                    if let Some(confirmed) = self.handle_confirmation_key(key.code) {
+                        action = Some(confirmed);
+                        self.should_quit = true;
+                    }
+                } else if self.show_details {
+                    // Any key closes the details popup
+                    self.show_details = false;
+                } else if self.form.is_some() {
+                    self.handle_form_key(key.code);
+                } else if self.flags_form.is_some() {
+                    self.handle_flags_key(key.code);
+                } else if self.rename.is_some() {
+                    if let Some(rename) = self.handle_rename_key(key.code) {
+                        action = Some(rename);
+                        self.should_quit = true;
+                    }
+                } else if self.filter.is_some() {
+                    // Filter mode - typed characters narrow the list
+                    match key.code {
+                        KeyCode::Esc => {
+                            self.filter = None;
+                            self.apply_filter();
+                        }
+                        KeyCode::Backspace => {
+                            if let Some(ref mut query) = self.filter {
+                                query.pop();
+                            }
+                            self.apply_filter();
+                        }
+                        KeyCode::Down => {
+                            self.next();
+                        }
+                        KeyCode::Up => {
+                            self.previous();
+                        }
+                        KeyCode::Enter => {
+                            if let Some(index) = self.selected_option_index() {
+                                action = self.request_switch(index);
+                                self.should_quit = action.is_some();
+                            }
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(ref mut query) = self.filter {
+                                query.push(c);
+                            }
+                            self.apply_filter();
+                        }
                         _ => {}
                     }
                 } else {
                     // Normal navigation mode
+                    let pending_g = std::mem::take(&mut self.pending_g);
+                    let copied_ip = self.copied_ip.take();
                     match key.code {
                         KeyCode::Char('q') => {
                             action = Some(AppAction::Quit);
@@ -118,9 +693,54 @@ impl App {
                         KeyCode::Up | KeyCode::Char('k') => {
                             self.previous();
                         }
+                        KeyCode::Char('g') => {
+                            // gg jumps to the top, like vim
+                            if pending_g {
+                                self.select_visible(0);
+                            } else {
+                                self.pending_g = true;
+                            }
+                        }
+                        KeyCode::Home => self.select_visible(0),
+                        KeyCode::PageDown => self.page(true),
+                        KeyCode::PageUp => self.page(false),
+                        KeyCode::Char('G') | KeyCode::End => {
+                            self.select_visible(self.visible.len().saturating_sub(1));
+                        }
+                        KeyCode::Char('.') => {
+                            // Jump to the active tailnet
+                            match self.get_active_profile_id() {
+                                Some(id) => {
+                                    self.select_tailnet(&id);
+                                }
+                                None => {
+                                    self.status_message =
+                                        Some("Not connected to any tailnet".to_string());
+                                }
+                            }
+                        }
+                        KeyCode::Char('n') => {
+                            // Diagnose connectivity with tailscale netcheck
+                            action = Some(AppAction::Netcheck);
+                            self.should_quit = true;
+                        }
+                        KeyCode::Char('y') => {
+                            // Copy this node's tailscale IP
+                            self.copy_ip(copied_ip);
+                        }
                         KeyCode::Char('u') => {
                             // Run tailscale up with configured flags
-                            action = Some(AppAction::RunTailscaleUp);
+                            action = self.tailscale_up_action();
+                            self.should_quit = action.is_some();
+                        }
+                        KeyCode::Char('R') => {
+                            // Re-authenticate before the node key expires
+                            action = self.reauthenticate_action();
+                            self.should_quit = action.is_some();
+                        }
+                        KeyCode::Char('r') => {
+                            // Re-query profiles and status
+                            action = Some(AppAction::RefreshProfiles);
                             self.should_quit = true;
                         }
                         KeyCode::Char('s') => {
@@ -129,22 +749,92 @@ impl App {
                             self.should_quit = true;
                         }
                         KeyCode::Char('l') => {
-                            // Logout from current profile
-                            action = Some(AppAction::Logout);
+                            // Logout from current profile, after confirmation
+                            let current = self
+                                .get_active_tailnet_name()
+                                .unwrap_or_else(|| "the current tailnet".to_string());
+                            self.confirmation = Some(Confirmation {
+                                message: format!("Log out of {}? (y/N)", current),
+                                on_confirm: ConfirmAction::Logout,
+                            });
+                        }
+                        KeyCode::Char('/') => {
+                            // Enter filter mode
+                            self.filter = Some(String::new());
+                        }
+                        KeyCode::Char('a') => {
+                            // Add a new tailnet to the config
+                            let empty = Tailnet::default();
+                            self.form = Some(TailnetForm::from_tailnet(&empty, None));
+                        }
+                        KeyCode::Char('f') => {
+                            // Toggle common flags for the selected tailnet
+                            self.open_flags_form();
+                        }
+                        KeyCode::Char('i') => {
+                            // Show the config that applies to the selected tailnet
+                            self.show_details = self.selected_option_index().is_some();
+                        }
+                        KeyCode::Char('e') => {
+                            // Edit the config entry for the selected tailnet
+                            self.open_edit_form();
+                        }
+                        KeyCode::Char('d') => {
+                            // Delete the selected profile or config entry
+                            self.confirm_delete();
+                        }
+                        KeyCode::Char('N') => self.open_rename_prompt(),
+                        KeyCode::Char('A') => {
+                            action = Some(AppAction::ToggleAcceptRoutes);
+                            self.should_quit = true;
+                        }
+                        KeyCode::Char('D') => {
+                            action = Some(AppAction::ToggleAcceptDns);
+                            self.should_quit = true;
+                        }
+                        KeyCode::Char('x') => {
+                            // Load exit nodes, then show the picker
+                            action = Some(AppAction::PickExitNode);
                             self.should_quit = true;
                         }
                         KeyCode::Enter => {
-                            if let Some(index) = self.list_state.selected()
-                                && index < self.options.len()
-                            {
-                                let (name, _, _, _) = &self.options[index];
-                                action = Some(AppAction::SelectTailnet(Tailnet {
-                                    name: name.clone(),
-                                    login_server: None,
-                                    auth_key: None,
-                                    flags: None,
-                                }));
-                                self.should_quit = true;
+                            if let Some(index) = self.selected_option_index() {
+                                action = self.request_switch(index);
+                                self.should_quit = action.is_some();
+                            }
+                        }
+                        KeyCode::Char(c @ '1'..='9') => {
+                            // Switch straight to the nth visible row
+                            let position = c as usize - '1' as usize;
+                            if position < self.visible.len() {
+                                self.list_state.select(Some(position));
+                                if let Some(index) = self.selected_option_index() {
+                                    action = self.request_switch(index);
+                                    self.should_quit = action.is_some();
+                                }
+                            }
+                        }
+                        KeyCode::Char('p') => {
+                            // Flip back to the previously active tailnet
+                            let previous = self.previous_tailnet.as_ref().map(|key| {
+                                self.options.iter().position(|o| o.key() == key).ok_or(key)
+                            });
+                            match previous {
+                                Some(Ok(index)) => {
+                                    action = self.request_switch(index);
+                                    self.should_quit = action.is_some();
+                                }
+                                Some(Err(name)) => {
+                                    action = Some(AppAction::SelectTailnet(Tailnet {
+                                        name: name.clone(),
+                                        ..Default::default()
+                                    }));
+                                    self.should_quit = true;
+                                }
+                                None => {
+                                    self.status_message =
+                                        Some("No previous tailnet to switch back to".to_string());
+                                }
                             }
                         }
                         _ => {}
@@ -152,6 +842,15 @@ impl App {
                 }
             }
 
+            // Offline, anything that needs tailscale would only fail obscurely
+            if self.offline && action.as_ref().is_some_and(AppAction::needs_tailscale) {
+                action = None;
+                self.should_quit = false;
+                self.status_message = Some(
+                    "✗ tailscale unavailable - start tailscaled and press r to retry".to_string(),
+                );
+            }
+
             if self.should_quit {
                 break;
             }
@@ -161,168 +860,1950 @@ impl App {
     }
 
     fn ui(&mut self, f: &mut Frame) {
-        if let Some(ref output) = self.output_view {
+        if let Some(ref mut output) = self.output_view {
             // Render output view
-            self.render_output_view(f, output);
+            Self::render_output_view(f, &self.theme, output);
+        } else if let Some(ref mut peers) = self.peers_view {
+            Self::render_peers_view(f, &self.theme, peers);
+        } else if let Some(ref mut picker) = self.exit_node_picker {
+            Self::render_exit_node_picker(f, &self.theme, picker);
         } else {
-            // Render normal list view
+            // Render normal list view, with a health banner only when there's something to report
+            let warnings = if self.offline {
+                1
+            } else {
+                self.status
+                    .as_ref()
+                    .map(|status| status.health.len())
+                    .unwrap_or(0)
+            };
+            let banner_height = if warnings > 0 {
+                warnings.min(MAX_HEALTH_LINES) as u16 + 2
+            } else {
+                0
+            };
+
+            // Extra lines for the version subtitle once it's known and a key expiry warning
+            let header_height = 3
+                + u16::from(self.version.is_some())
+                + u16::from(self.key_expiry_warning().is_some());
+
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .margin(2)
                 .constraints([
-                    Constraint::Length(3),
+                    Constraint::Length(header_height),
+                    Constraint::Length(banner_height),
                     Constraint::Min(0),
                     Constraint::Length(3),
                 ])
                 .split(f.area());
 
             self.render_header(f, chunks[0]);
-            self.render_tailnet_list(f, chunks[1]);
-            self.render_footer(f, chunks[2]);
+            if warnings > 0 {
+                self.render_health_banner(f, chunks[1]);
+            }
+            // Status panel beside the list when there's room for both
+            if chunks[2].width >= TWO_PANE_MIN_WIDTH {
+                let panes = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(chunks[2]);
+                self.render_tailnet_list(f, panes[0]);
+                self.render_status_panel(f, panes[1]);
+            } else {
+                self.render_tailnet_list(f, chunks[2]);
+            }
+            self.render_footer(f, chunks[3]);
+
+            if let Some(ref form) = self.form {
+                Self::render_form(f, &self.theme, form);
+            }
+            if let Some(ref form) = self.flags_form {
+                Self::render_flags_form(f, &self.theme, form);
+            }
+            if let Some(ref confirmation) = self.confirmation {
+                Self::render_confirmation(f, &self.theme, confirmation);
+            }
+            if let Some(ref prompt) = self.rename {
+                Self::render_rename_prompt(f, &self.theme, prompt);
+            }
+            if self.show_details {
+                self.render_details(f);
+            }
+        }
+
+        if self.show_help {
+            let (title, entries) = if self.output_view.is_some() {
+                ("Output", OUTPUT_HELP)
+            } else if self.peers_view.is_some() {
+                ("Status", PEERS_HELP)
+            } else if self.exit_node_picker.is_some() {
+                ("Exit Node", EXIT_NODE_HELP)
+            } else {
+                ("Tailnets", LIST_HELP)
+            };
+            render_help(f, &self.theme, title, entries);
+        }
+    }
+
+    /// Handle a key in the exit node picker. Returns the action to apply a selection.
+    fn handle_exit_node_key(&mut self, code: KeyCode) -> Option<AppAction> {
+        let picker = self.exit_node_picker.as_mut()?;
+
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => picker.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => picker.move_selection(-1),
+            KeyCode::Esc => self.exit_node_picker = None,
+            KeyCode::Char('q') => {
+                self.exit_node_picker = None;
+                return Some(AppAction::Quit);
+            }
+            KeyCode::Enter => {
+                let node = match picker.list_state.selected() {
+                    Some(0) | None => None,
+                    Some(i) => picker.nodes.get(i - 1).map(|node| node.ip.clone()),
+                };
+                self.exit_node_picker = None;
+                return Some(AppAction::SetExitNode(node));
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn render_exit_node_picker(f: &mut Frame, theme: &Theme, picker: &mut ExitNodePicker) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(f.area());
+
+        let title = Paragraph::new("Exit Node")
+            .style(
+                Style::default()
+                    .fg(theme.header)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        let marker = |active: bool| {
+            if active {
+                Span::styled("★ ", Style::default().fg(theme.warning))
+            } else {
+                Span::styled("  ", Style::default())
+            }
+        };
+
+        let mut items = vec![ListItem::new(Line::from(vec![
+            marker(picker.current.is_none()),
+            Span::styled("None (disable exit node)", Style::default().fg(theme.text)),
+        ]))];
+        for node in &picker.nodes {
+            let active = picker.is_current(node);
+            let location = [node.city.as_str(), node.country.as_str()]
+                .into_iter()
+                .filter(|part| !part.is_empty() && *part != "-")
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let mut spans = vec![
+                marker(active),
+                Span::styled(
+                    node.hostname.as_str(),
+                    Style::default()
+                        .fg(if active { theme.active } else { theme.text })
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!("  {}", node.ip), Style::default().fg(theme.muted)),
+            ];
+            if !location.is_empty() {
+                spans.push(Span::styled(
+                    format!("  {}", location),
+                    Style::default().fg(theme.dim),
+                ));
+            }
+            if !node.status.is_empty() && node.status != "-" {
+                spans.push(Span::styled(
+                    format!("  ({})", node.status),
+                    Style::default().fg(theme.dim),
+                ));
+            }
+            items.push(ListItem::new(Line::from(spans)));
         }
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(match picker.current {
+                        Some(ref current) => {
+                            format!("Available exit nodes (using {})", current.name)
+                        }
+                        None => "Available exit nodes".to_string(),
+                    }),
+            )
+            .highlight_style(theme.selection.add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut picker.list_state);
+
+        let footer = Paragraph::new("j/k: navigate | Enter: use exit node | Esc: back | q: quit")
+            .style(Style::default().fg(theme.muted))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+    }
+
+    fn confirm_delete(&mut self) {
+        let Some(index) = self.selected_option_index() else {
+            return;
+        };
+        let option = &self.options[index];
+
+        self.confirmation = Some(if option.is_profile {
+            Confirmation {
+                message: format!(
+                    "Remove the tailscale profile '{}'? You will need to log in again to use it.",
+                    option.name
+                ),
+                on_confirm: ConfirmAction::DeleteProfile(option.clone()),
+            }
+        } else {
+            Confirmation {
+                message: format!("Remove '{}' from the config file?", option.name),
+                on_confirm: ConfirmAction::DeleteConfigEntry(option.name.clone()),
+            }
+        });
+    }
+
+    /// Ask for a new name for the selected profile
+    fn open_rename_prompt(&mut self) {
+        let Some(index) = self.selected_option_index() else {
+            return;
+        };
+        let option = &self.options[index];
+        if !option.is_profile {
+            self.status_message = Some(
+                "Only tailscale profiles can be renamed; press e to edit this entry".to_string(),
+            );
+            return;
+        }
+
+        self.rename = Some(RenamePrompt {
+            option: option.clone(),
+            name: option.account.clone().unwrap_or_default(),
+        });
+    }
+
+    /// Handle a key in the rename prompt. Returns the rename to run on Enter.
+    fn handle_rename_key(&mut self, code: KeyCode) -> Option<AppAction> {
+        let prompt = self.rename.as_mut()?;
+        match code {
+            KeyCode::Esc => self.rename = None,
+            KeyCode::Backspace => {
+                prompt.name.pop();
+            }
+            KeyCode::Char(c) => prompt.name.push(c),
+            KeyCode::Enter => {
+                let prompt = self.rename.take()?;
+                let name = prompt.name.trim().to_string();
+                if name.is_empty() {
+                    self.status_message = Some("✗ The new name can't be empty".to_string());
+                    return None;
+                }
+                return Some(AppAction::RenameProfile(prompt.option, name));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn render_rename_prompt(f: &mut Frame, theme: &Theme, prompt: &RenamePrompt) {
+        let area = centered_rect(50, 8, f.area());
+
+        let text = vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                match prompt.option.account {
+                    Some(ref account) => {
+                        format!("New name for {} on {}:", account, prompt.option.label())
+                    }
+                    None => format!("New name for the {} profile:", prompt.option.label()),
+                },
+                Style::default().fg(theme.text),
+            )),
+            Line::from(""),
+            Line::from(Span::styled(
+                format!("{}_", prompt.name),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from(Span::styled(
+                "Enter: rename | Esc: cancel",
+                Style::default().fg(theme.muted),
+            )),
+        ];
+
+        let popup = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent))
+                .title("Rename Profile"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    /// Handle a key while the confirmation prompt is open. Only 'y' confirms;
+    /// every other key cancels. Returns an action if one must run outside the TUI.
+    fn handle_confirmation_key(&mut self, code: KeyCode) -> Option<AppAction> {
+        let confirmation = self.confirmation.take()?;
+        if !matches!(code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            return None;
+        }
+
+        match confirmation.on_confirm {
+            ConfirmAction::SwitchTailnet(tailnet) => Some(AppAction::SelectTailnet(tailnet)),
+            ConfirmAction::Logout => Some(AppAction::Logout),
+            ConfirmAction::DeleteProfile(option) => Some(AppAction::DeleteProfile(option)),
+            ConfirmAction::DeleteConfigEntry(name) => {
+                self.delete_config_entry(&name);
+                None
+            }
+        }
+    }
+
+    fn delete_config_entry(&mut self, name: &str) {
+        let mut updated = self.config.clone();
+        updated.tailnets.retain(|t| t.name != name);
+
+        match updated.save() {
+            Ok(()) => {
+                self.config = updated;
+                self.refresh_config_options();
+                self.status_message = Some(format!("✓ Removed '{}' from config", name));
+            }
+            Err(e) => {
+                self.status_message = Some(format!("✗ Failed to save config: {:#}", e));
+            }
+        }
+    }
+
+    /// Popup with the config entry for the highlighted row and the flags that
+    /// would actually be applied (after env expansion and `default_flags`)
+    fn render_details(&self, f: &mut Frame) {
+        let Some(option) = self.selected_option_index().map(|i| &self.options[i]) else {
+            return;
+        };
+        let entry = self.config.tailnets.iter().find(|t| t.name == option.name);
+
+        let label =
+            |text: &str| Span::styled(format!("{:<14}", text), Style::default().fg(self.theme.dim));
+        let value = |text: String| Span::styled(text, Style::default().fg(self.theme.text));
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+        let kind = match (option.is_profile, entry.is_some()) {
+            (true, true) => "tailscale profile + config entry",
+            (true, false) => "tailscale profile (no config entry)",
+            (false, _) => "config entry (not logged in yet)",
+        };
+        let mut lines = vec![Line::from(vec![
+            label("Name:"),
+            value(option.label().to_string()),
+        ])];
+        if option.display_name.is_some() {
+            lines.push(Line::from(vec![
+                label("Tailnet:"),
+                value(option.name.clone()),
+            ]));
+        }
+        lines.push(Line::from(vec![label("Type:"), value(kind.to_string())]));
+        if let Some(ref account) = option.account {
+            lines.push(Line::from(vec![label("Account:"), value(account.clone())]));
+        }
+        if let Some(ref id) = option.profile_id {
+            lines.push(Line::from(vec![label("Profile ID:"), value(id.clone())]));
+        }
+
+        if let Some(tailnet) = entry {
+            let auth_key = if tailnet.auth_key.is_some() {
+                "**** (inline)".to_string()
+            } else if let Some(ref file) = tailnet.auth_key_file {
+                format!("from file {}", file)
+            } else if let Some(ref command) = tailnet.auth_key_command {
+                format!("from command `{}`", command)
+            } else {
+                "not set".to_string()
+            };
+            let flags = tailnet
+                .flags
+                .as_ref()
+                .map(|flags| runner::redact_secrets(&flags.join(" ")));
+
+            lines.push(Line::from(vec![
+                label("Login server:"),
+                value(or_none(tailnet.login_server.clone())),
+            ]));
+            lines.push(Line::from(vec![label("Auth key:"), value(auth_key)]));
+            lines.push(Line::from(vec![label("Flags:"), value(or_none(flags))]));
+            let mut advertised: Vec<String> = tailnet.advertise_routes.clone().unwrap_or_default();
+            if tailnet.advertise_exit_node == Some(true) {
+                advertised.push("exit node".to_string());
+            }
+            if tailnet.advertise_routes.is_some() || tailnet.advertise_exit_node.is_some() {
+                let advertised = if advertised.is_empty() {
+                    "nothing".to_string()
+                } else {
+                    advertised.join(", ")
+                };
+                lines.push(Line::from(vec![label("Advertises:"), value(advertised)]));
+            }
+            if let Some(ref command) = tailnet.post_switch {
+                lines.push(Line::from(vec![
+                    label("Post-switch:"),
+                    value(command.clone()),
+                ]));
+            }
+        }
+
+        // What `u` / a login would actually pass to tailscale
+        let applied = match self.config.resolve_tailnet(&option.name) {
+            Ok(resolved) => or_none(resolved.flags.map(|flags| flags.join(" "))),
+            Err(e) => format!("✗ {:#}", e),
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            label("Applied flags:"),
+            value(runner::redact_secrets(&applied)),
+        ]));
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Press any key to close",
+            Style::default().fg(self.theme.muted),
+        )));
+
+        let area = centered_rect(70, lines.len() as u16 + 2, f.area());
+        let popup = Paragraph::new(lines)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.accent))
+                    .title("Details"),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    fn render_confirmation(f: &mut Frame, theme: &Theme, confirmation: &Confirmation) {
+        let area = centered_rect(50, 8, f.area());
+
+        let text = vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                confirmation.message.as_str(),
+                Style::default().fg(theme.text).add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled(
+                    "y",
+                    Style::default()
+                        .fg(theme.active)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(": yes  |  ", Style::default().fg(theme.muted)),
+                Span::styled(
+                    "any other key",
+                    Style::default()
+                        .fg(theme.error)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(": cancel", Style::default().fg(theme.muted)),
+            ]),
+        ];
+
+        let popup = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(ratatui::widgets::Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.warning))
+                    .title("Confirm"),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    fn open_edit_form(&mut self) {
+        let Some(name) = self.get_selected_tailnet_name() else {
+            return;
+        };
+
+        // Existing profiles without a config entry start from just the name
+        let editing = self.config.tailnets.iter().position(|t| t.name == name);
+        let tailnet = match editing {
+            Some(index) => self.config.tailnets[index].clone(),
+            None => Tailnet {
+                name,
+                ..Default::default()
+            },
+        };
+        self.form = Some(TailnetForm::from_tailnet(&tailnet, editing));
+    }
+
+    fn handle_form_key(&mut self, code: KeyCode) {
+        let Some(ref mut form) = self.form else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.form = None;
+            }
+            KeyCode::Tab | KeyCode::Down => {
+                form.focus = (form.focus + 1) % FORM_LABELS.len();
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                form.focus = (form.focus + FORM_LABELS.len() - 1) % FORM_LABELS.len();
+            }
+            KeyCode::Backspace => {
+                form.fields[form.focus].pop();
+            }
+            KeyCode::Char(c) => {
+                form.fields[form.focus].push(c);
+            }
+            KeyCode::Enter => {
+                self.save_form();
+            }
+            _ => {}
+        }
+    }
+
+    /// Validate the form, write it to the config file and refresh the list
+    fn save_form(&mut self) {
+        let Some(form) = self.form.take() else {
+            return;
+        };
+
+        let name = form.fields[0].trim().to_string();
+        if name.is_empty() {
+            self.status_message = Some("✗ Tailnet name cannot be empty".to_string());
+            self.form = Some(form);
+            return;
+        }
+
+        let duplicate = self
+            .config
+            .tailnets
+            .iter()
+            .enumerate()
+            .any(|(index, t)| t.name == name && Some(index) != form.editing);
+        if duplicate {
+            self.status_message = Some(format!("✗ A tailnet named '{}' already exists", name));
+            self.form = Some(form);
+            return;
+        }
+
+        let mut updated = self.config.clone();
+        match form.editing {
+            Some(index) => form.apply_to(&mut updated.tailnets[index]),
+            None => {
+                let mut tailnet = Tailnet::default();
+                form.apply_to(&mut tailnet);
+                updated.tailnets.push(tailnet);
+            }
+        }
+
+        if !self.save_config(updated, &name) {
+            self.form = Some(form);
+        }
+    }
+
+    /// Validate and write an updated config, then refresh the list. Reports
+    /// the outcome in the footer and returns whether it was saved.
+    fn save_config(&mut self, updated: Config, name: &str) -> bool {
+        if let Err(e) = updated.validate() {
+            self.status_message = Some(format!("✗ {:#}", e));
+            return false;
+        }
+
+        match updated.save() {
+            Ok(()) => {
+                self.config = updated;
+                self.refresh_config_options();
+                self.status_message = Some(format!("✓ Saved '{}' to config", name));
+                true
+            }
+            Err(e) => {
+                self.status_message = Some(format!("✗ Failed to save config: {:#}", e));
+                false
+            }
+        }
+    }
+
+    fn open_flags_form(&mut self) {
+        let Some(name) = self.get_selected_tailnet_name() else {
+            return;
+        };
+        let flags = self
+            .config
+            .tailnets
+            .iter()
+            .find(|t| t.name == name)
+            .and_then(|t| t.flags.clone())
+            .unwrap_or_default();
+        self.flags_form = Some(FlagsForm::from_flags(name, &flags));
+    }
+
+    fn handle_flags_key(&mut self, code: KeyCode) {
+        let Some(ref mut form) = self.flags_form else {
+            return;
+        };
+        let rows = FLAG_TOGGLES.len() + 1;
+        let in_text = form.focus == FLAG_TOGGLES.len();
+
+        match code {
+            KeyCode::Esc => self.flags_form = None,
+            KeyCode::Enter => self.save_flags_form(),
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % rows,
+            KeyCode::BackTab | KeyCode::Up => form.focus = (form.focus + rows - 1) % rows,
+            KeyCode::Backspace if in_text => {
+                form.other.pop();
+            }
+            KeyCode::Char(c) if in_text => form.other.push(c),
+            KeyCode::Char(' ') => form.checked[form.focus] = !form.checked[form.focus],
+            KeyCode::Char('j') => form.focus = (form.focus + 1) % rows,
+            KeyCode::Char('k') => form.focus = (form.focus + rows - 1) % rows,
+            _ => {}
+        }
+    }
+
+    /// Write the checkbox flags to the tailnet's config entry, adding one if needed
+    fn save_flags_form(&mut self) {
+        let Some(form) = self.flags_form.take() else {
+            return;
+        };
+
+        let flags = form.flags();
+        let flags = (!flags.is_empty()).then_some(flags);
+        let mut updated = self.config.clone();
+        match updated.tailnets.iter_mut().find(|t| t.name == form.name) {
+            Some(tailnet) => tailnet.flags = flags,
+            None => updated.tailnets.push(Tailnet {
+                name: form.name.clone(),
+                flags,
+                ..Default::default()
+            }),
+        }
+
+        if !self.save_config(updated, &form.name) {
+            self.flags_form = Some(form);
+        }
+    }
+
+    fn render_flags_form(f: &mut Frame, theme: &Theme, form: &FlagsForm) {
+        let area = centered_rect(60, FLAG_TOGGLES.len() as u16 + 5, f.area());
+        let style = |focused: bool| {
+            Style::default()
+                .fg(if focused { theme.accent } else { theme.text })
+                .add_modifier(if focused {
+                    Modifier::BOLD
+                } else {
+                    Modifier::empty()
+                })
+        };
+
+        let mut lines = vec![Line::from("")];
+        for (index, ((flag, _), checked)) in FLAG_TOGGLES.iter().zip(form.checked).enumerate() {
+            let focused = index == form.focus;
+            lines.push(Line::from(vec![
+                Span::styled(if focused { " > " } else { "   " }, style(focused)),
+                Span::styled(
+                    if checked { "[x] " } else { "[ ] " },
+                    Style::default().fg(if checked { theme.active } else { theme.muted }),
+                ),
+                Span::styled(*flag, style(focused)),
+            ]));
+        }
+
+        let focused = form.focus == FLAG_TOGGLES.len();
+        lines.push(Line::from(vec![
+            Span::styled(if focused { " > " } else { "   " }, style(focused)),
+            Span::styled("Other flags: ", Style::default().fg(theme.muted)),
+            Span::styled(form.other.as_str(), style(focused)),
+            Span::styled(
+                if focused { "_" } else { "" },
+                Style::default().fg(theme.accent),
+            ),
+        ]));
+
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Flags - {}", form.name))
+                .title_bottom("Space: toggle | j/k: move | Enter: save | Esc: cancel"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    /// Rebuild the config-only rows after the config changed, keeping profiles as-is
+    fn refresh_config_options(&mut self) {
+        let mut options: Vec<TailnetOption> = self
+            .options
+            .iter()
+            .filter(|option| option.is_profile)
+            .cloned()
+            .collect();
+        let config_only: Vec<_> = options::config_options(&self.config)
+            .into_iter()
+            .filter(|option| !options.iter().any(|o| o.name == option.name))
+            .collect();
+        options.extend(config_only);
+        options::apply_config_labels(&mut options, &self.config);
+
+        self.set_options(options);
+    }
+
+    fn render_form(f: &mut Frame, theme: &Theme, form: &TailnetForm) {
+        let area = centered_rect(60, FORM_LABELS.len() as u16 + 4, f.area());
+
+        let mut lines = vec![Line::from("")];
+        for (index, label) in FORM_LABELS.iter().enumerate() {
+            let focused = index == form.focus;
+            let value = if index == 2 {
+                // Don't show auth keys in plain text
+                "*".repeat(form.fields[index].chars().count())
+            } else if index == 3 && !focused {
+                // Nor ones passed as flags, except while they're being edited
+                runner::redact_secrets(&form.fields[index])
+            } else {
+                form.fields[index].clone()
+            };
+
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{:>13}: ", label),
+                    Style::default().fg(if focused { theme.accent } else { theme.muted }),
+                ),
+                Span::styled(
+                    value,
+                    Style::default().fg(theme.text).add_modifier(if focused {
+                        Modifier::BOLD
+                    } else {
+                        Modifier::empty()
+                    }),
+                ),
+                Span::styled(
+                    if focused { "_" } else { "" },
+                    Style::default().fg(theme.accent),
+                ),
+            ]));
+        }
+
+        let title = if form.editing.is_some() {
+            "Edit tailnet"
+        } else {
+            "Add tailnet"
+        };
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom("Tab: next field | Enter: save | Esc: cancel"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    fn render_header(&self, f: &mut Frame, area: Rect) {
+        let mut lines = vec![Line::from(Span::styled(
+            "TailSwitch - Tailscale Network Switcher",
+            Style::default()
+                .fg(self.theme.header)
+                .add_modifier(Modifier::BOLD),
+        ))];
+
+        if let Some(ref version) = self.version {
+            let mut subtitle = vec![Span::styled(
+                format!("tailscale {}", version),
+                Style::default().fg(self.theme.muted),
+            )];
+            let status = self.status.as_ref();
+            if status.is_some_and(StatusInfo::update_available) {
+                let update = match status.and_then(|s| s.latest_version.as_deref()) {
+                    Some(latest) => format!(" · update available: {}", latest),
+                    None => " · update available".to_string(),
+                };
+                subtitle.push(Span::styled(
+                    update,
+                    Style::default().fg(self.theme.warning),
+                ));
+            }
+            lines.push(Line::from(subtitle));
+        }
+
+        if let Some(warning) = self.key_expiry_warning() {
+            lines.push(Line::from(Span::styled(
+                warning,
+                Style::default().fg(self.theme.warning),
+            )));
+        }
+
+        let title = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, area);
+    }
+
+    /// Header warning when the node key expires within `key_expiry_warn_days`
+    fn key_expiry_warning(&self) -> Option<String> {
+        let left = self
+            .status
+            .as_ref()?
+            .key_expires_within(self.config.key_expiry_warning()?)?;
+        let days = left.as_secs() / (24 * 60 * 60);
+        let hours = left.as_secs() / (60 * 60);
+        let when = match (days, hours) {
+            (_, 0) if left.is_zero() => "has expired".to_string(),
+            (0, 0) => "expires within the hour".to_string(),
+            (0, 1) => "expires in 1 hour".to_string(),
+            (0, hours) => format!("expires in {} hours", hours),
+            (1, _) => "expires in 1 day".to_string(),
+            (days, _) => format!("expires in {} days", days),
+        };
+        Some(format!("⚠ Node key {} - press R to re-authenticate", when))
+    }
+
+    /// Health warnings from tailscaled, red for connectivity problems and yellow otherwise
+    fn render_health_banner(&self, f: &mut Frame, area: Rect) {
+        if self.offline {
+            let banner = Paragraph::new(Line::from(Span::styled(
+                "⚠ tailscale is unreachable - showing your config read-only. Press r to retry.",
+                Style::default().fg(self.theme.error),
+            )))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.error))
+                    .title("Offline"),
+            )
+            .wrap(ratatui::widgets::Wrap { trim: true });
+            f.render_widget(banner, area);
+            return;
+        }
+
+        let Some(ref status) = self.status else {
+            return;
+        };
+
+        let lines: Vec<Line> = status
+            .health
+            .iter()
+            .map(|warning| {
+                let color = if warning.severe {
+                    self.theme.error
+                } else {
+                    self.theme.warning
+                };
+                Line::from(Span::styled(
+                    format!("⚠ {}", warning.message),
+                    Style::default().fg(color),
+                ))
+            })
+            .collect();
+
+        let border = if status.health.iter().any(|warning| warning.severe) {
+            self.theme.error
+        } else {
+            self.theme.warning
+        };
+        let banner = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(border))
+                    .title("Health"),
+            )
+            .wrap(ratatui::widgets::Wrap { trim: true });
+        f.render_widget(banner, area);
+    }
+
+    /// What this node serves to the tailnet, with warnings where that differs
+    /// from the active tailnet's advertise_routes / advertise_exit_node
+    fn advertised_lines<'a>(
+        &self,
+        status: &StatusInfo,
+        label: impl Fn(&str) -> Span<'a>,
+        value: impl Fn(String) -> Span<'a>,
+    ) -> Vec<Line<'a>> {
+        let entry = self
+            .get_active_tailnet_name()
+            .and_then(|name| self.config.tailnets.iter().find(|t| t.name == name));
+        let configured_routes = entry.and_then(|t| t.advertise_routes.as_deref());
+        let configured_exit_node = entry.and_then(|t| t.advertise_exit_node);
+        let warning = |text: String| {
+            Line::from(Span::styled(
+                format!("⚠ {}", text),
+                Style::default().fg(self.theme.warning),
+            ))
+        };
+
+        let mut lines = Vec::new();
+        if !status.advertised_routes.is_empty() || configured_routes.is_some() {
+            let routes = if status.advertised_routes.is_empty() {
+                "none".to_string()
+            } else {
+                status.advertised_routes.join(", ")
+            };
+            lines.push(Line::from(vec![label("Routes:"), value(routes)]));
+        }
+        if let Some(configured) = configured_routes {
+            let (missing, extra) = status.route_differences(configured);
+            if !missing.is_empty() {
+                lines.push(warning(format!(
+                    "Not advertised: {} (awaiting approval?)",
+                    missing.join(", ")
+                )));
+            }
+            if !extra.is_empty() {
+                lines.push(warning(format!("Not in config: {}", extra.join(", "))));
+            }
+        }
+
+        if status.offers_exit_node || configured_exit_node.is_some() {
+            let offer = if status.offers_exit_node {
+                "offered as exit node"
+            } else {
+                "not an exit node"
+            };
+            lines.push(Line::from(vec![label("Offers:"), value(offer.to_string())]));
+        }
+        match configured_exit_node {
+            Some(true) if !status.offers_exit_node => lines.push(warning(
+                "Exit node configured but not offered (awaiting approval?)".to_string(),
+            )),
+            Some(false) if status.offers_exit_node => lines.push(warning(
+                "Offered as exit node, but the config turns it off".to_string(),
+            )),
+            _ => {}
+        }
+        lines
+    }
+
+    /// Live connection status and details of the highlighted row
+    fn render_status_panel(&self, f: &mut Frame, area: Rect) {
+        let label =
+            |text: &str| Span::styled(format!("{:<11}", text), Style::default().fg(self.theme.dim));
+        let value = |text: String| Span::styled(text, Style::default().fg(self.theme.text));
+        let heading = |text: &'static str| {
+            Line::from(Span::styled(
+                text,
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ))
+        };
+
+        let mut lines = vec![heading("Connection")];
+        match self.status {
+            Some(ref status) => {
+                let state_color = if status.backend_state == "Running" {
+                    self.theme.active
+                } else {
+                    self.theme.warning
+                };
+                lines.push(Line::from(vec![
+                    label("State:"),
+                    Span::styled(
+                        status.backend_state.clone(),
+                        Style::default().fg(state_color),
+                    ),
+                ]));
+                lines.push(Line::from(vec![
+                    label("Tailnet:"),
+                    value(
+                        status
+                            .tailnet_name
+                            .clone()
+                            .unwrap_or_else(|| "-".to_string()),
+                    ),
+                ]));
+                if let Some(ref login) = status.login_name {
+                    lines.push(Line::from(vec![label("Account:"), value(login.clone())]));
+                }
+                lines.push(Line::from(vec![
+                    label("IP:"),
+                    value(
+                        status
+                            .self_ips
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "-".to_string()),
+                    ),
+                ]));
+                if let Some(ref hostname) = status.self_hostname {
+                    lines.push(Line::from(vec![
+                        label("Hostname:"),
+                        value(hostname.clone()),
+                    ]));
+                }
+                if let Some(ref dns_name) = status.self_dns_name {
+                    lines.push(Line::from(vec![
+                        label("DNS name:"),
+                        value(dns_name.clone()),
+                    ]));
+                }
+                lines.push(Line::from(vec![
+                    label("MagicDNS:"),
+                    if status.magic_dns {
+                        value("on".to_string())
+                    } else {
+                        Span::styled(
+                            "off - peers are only reachable by IP",
+                            Style::default().fg(self.theme.dim),
+                        )
+                    },
+                ]));
+                lines.push(Line::from(vec![
+                    label("Peers:"),
+                    value(format!(
+                        "{} online / {} total",
+                        status.online_peers,
+                        status.online_peers + status.offline_peers
+                    )),
+                ]));
+                let (direct, relayed) = status.connection_counts();
+                if direct + relayed > 0 {
+                    lines.push(Line::from(vec![
+                        label("Links:"),
+                        value(format!("{} direct, {} relayed", direct, relayed)),
+                    ]));
+                }
+                lines.push(Line::from(vec![
+                    label("Exit node:"),
+                    value(match status.exit_node {
+                        Some(ref node) => node.name.clone(),
+                        None => "none".to_string(),
+                    }),
+                ]));
+                lines.extend(self.advertised_lines(status, label, value));
+            }
+            None => lines.push(Line::from(label("Status unavailable"))),
+        }
+
+        if let Some(option) = self.selected_option_index().map(|i| &self.options[i]) {
+            lines.push(Line::from(""));
+            lines.push(heading("Selected"));
+            lines.push(Line::from(vec![
+                label("Name:"),
+                value(option.label().to_string()),
+            ]));
+            if option.display_name.is_some() {
+                lines.push(Line::from(vec![
+                    label("Tailnet:"),
+                    value(option.name.clone()),
+                ]));
+            }
+            if let Some(ref account) = option.account {
+                lines.push(Line::from(vec![label("Account:"), value(account.clone())]));
+            }
+            if let Some(ref id) = option.profile_id {
+                lines.push(Line::from(vec![label("Profile ID:"), value(id.clone())]));
+            }
+            let state = if option.is_active {
+                "connected"
+            } else if option.needs_login {
+                "logged out"
+            } else if option.is_profile {
+                "profile"
+            } else {
+                "not logged in yet (config only)"
+            };
+            lines.push(Line::from(vec![label("State:"), value(state.to_string())]));
+        }
+
+        let panel = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Status"))
+            .wrap(ratatui::widgets::Wrap { trim: false });
+        f.render_widget(panel, area);
+    }
+
+    fn render_tailnet_list(&mut self, f: &mut Frame, area: Rect) {
+        // Inside the border, for paging
+        self.list_height = area.height.saturating_sub(2) as usize;
+        self.list_area = area;
+
+        // Room for a row's text: inside the border, after the highlight symbol
+        let width = (area.width as usize).saturating_sub(2 + 3);
+        let rows = self.list_rows();
+        let items: Vec<ListItem> = rows
+            .iter()
+            .enumerate()
+            .map(|(row, &entry)| {
+                let Some(position) = entry else {
+                    let group = rows[row + 1].and_then(|next| self.group_header(next));
+                    let group = truncate(group.unwrap_or_default(), width.saturating_sub(6));
+                    return ListItem::new(Line::from(Span::styled(
+                        format!("── {} ──", group),
+                        Style::default()
+                            .fg(self.theme.accent)
+                            .add_modifier(Modifier::BOLD),
+                    )));
+                };
+                let option = &self.options[self.visible[position]];
+                let mut lines = vec![];
+
+                // 1-9 hotkey hint for the first nine visible rows
+                let hotkey = if position < 9 {
+                    Span::styled(
+                        format!("{} ", position + 1),
+                        Style::default().fg(self.theme.dim),
+                    )
+                } else {
+                    Span::styled("  ", Style::default())
+                };
+
+                if option.is_profile {
+                    // Existing profile - show with checkmark and star if active
+                    let prefix = if option.is_active {
+                        Span::styled("★ ", Style::default().fg(self.theme.warning))
+                    } else if option.needs_login {
+                        Span::styled("⚠ ", Style::default().fg(self.theme.warning))
+                    } else {
+                        Span::styled("  ", Style::default())
+                    };
+
+                    let suffix = if option.is_active {
+                        let label = match self.status {
+                            Some(ref status) => {
+                                format!(" (active · {} peers online)", status.online_peers)
+                            }
+                            None => " (active)".to_string(),
+                        };
+                        Span::styled(label, Style::default().fg(self.theme.active))
+                    } else {
+                        Span::styled(" (not connected)", Style::default().fg(self.theme.dim))
+                    };
+                    let name_width = width.saturating_sub(4 + suffix.width());
+                    lines.push(Line::from(vec![
+                        hotkey,
+                        prefix,
+                        Span::styled(
+                            truncate(option.label(), name_width.max(MIN_NAME_WIDTH)),
+                            Style::default()
+                                .fg(self.theme.text)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        suffix,
+                    ]));
+
+                    if let Some(ref acc) = option.account {
+                        // Several accounts on one tailnet: the ID tells the rows apart
+                        let shared = self
+                            .options
+                            .iter()
+                            .filter(|o| o.is_profile && o.name == option.name)
+                            .count()
+                            > 1;
+                        let id = match option.profile_id {
+                            Some(ref id) if shared => format!(" · {}", id),
+                            _ => String::new(),
+                        };
+                        let account_width = width.saturating_sub(6 + id.width());
+                        lines.push(Line::from(vec![
+                            Span::styled("      ", Style::default()),
+                            Span::styled(
+                                truncate(acc, account_width.max(MIN_NAME_WIDTH)),
+                                Style::default().fg(self.theme.muted),
+                            ),
+                            Span::styled(id, Style::default().fg(self.theme.dim)),
+                        ]));
+                    }
+                } else {
+                    // New profile from config - show with plus
+                    let name_width = width.saturating_sub(4 + " (add new)".len());
+                    lines.push(Line::from(vec![
+                        hotkey,
+                        Span::styled("+ ", Style::default().fg(self.theme.warning)),
+                        Span::styled(
+                            truncate(option.label(), name_width.max(MIN_NAME_WIDTH)),
+                            Style::default().fg(self.theme.text),
+                        ),
+                        Span::styled(" (add new)", Style::default().fg(self.theme.dim)),
+                    ]));
+                }
+
+                ListItem::new(lines)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("j/k: navigate | Enter/1-9: select | /: filter | ?: help | q: quit"),
+            )
+            .highlight_style(self.theme.selection.add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+
+        // The selection counts tailnets only, so map it onto the rows with headers
+        let selected = self
+            .list_state
+            .selected()
+            .and_then(|position| rows.iter().position(|&row| row == Some(position)));
+        let mut offset = self.list_offset;
+        // Keep a group's header in view along with its first tailnet
+        if let Some(row) = selected
+            && row > 0
+            && rows[row - 1].is_none()
+        {
+            offset = offset.min(row - 1);
+        }
+        let mut state = ListState::default()
+            .with_offset(offset)
+            .with_selected(selected);
+        f.render_stateful_widget(list, area, &mut state);
+        self.list_offset = state.offset();
+
+        // Only show a scrollbar when the rows don't all fit
+        let total_rows: usize = rows
+            .iter()
+            .map(|entry| {
+                entry.map_or(1, |position| {
+                    item_height(&self.options[self.visible[position]])
+                })
+            })
+            .sum();
+        if total_rows > self.list_height {
+            let mut scrollbar_state = ScrollbarState::new(self.visible.len())
+                .position(self.list_state.selected().unwrap_or(0));
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                area,
+                &mut scrollbar_state,
+            );
+        }
+    }
+
+    fn render_footer(&self, f: &mut Frame, area: Rect) {
+        let config_path = self.config.path().display();
+        let footer_text = if let Some(ref query) = self.filter {
+            format!(
+                "/{}_  ({} of {} shown, Esc to clear)",
+                query,
+                self.visible.len(),
+                self.options.len()
+            )
+        } else if let Some(ref msg) = self.status_message {
+            msg.clone()
+        } else if let Some(ref prefs) = self.prefs {
+            let on_off = |value: bool| if value { "on" } else { "off" };
+            format!(
+                "Config: {} | A: accept-routes {} | D: accept-dns {}",
+                config_path,
+                on_off(prefs.accept_routes),
+                on_off(prefs.accept_dns)
+            )
+        } else {
+            format!("Config: {}", config_path)
+        };
+
+        let footer = Paragraph::new(footer_text)
+            .style(Style::default().fg(self.theme.muted))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, area);
+    }
+
+    fn next(&mut self) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i >= self.visible.len() - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    /// Move the selection by about one screenful
+    fn page(&mut self, down: bool) {
+        if self.visible.is_empty() {
+            return;
+        }
+
+        let mut position = self.list_state.selected().unwrap_or(0);
+        let mut rows = 0;
+        loop {
+            let next = if down {
+                position + 1
+            } else {
+                match position.checked_sub(1) {
+                    Some(next) => next,
+                    None => break,
+                }
+            };
+            let Some(&index) = self.visible.get(next) else {
+                break;
+            };
+            rows += item_height(&self.options[index]);
+            if rows > self.list_height.max(1) {
+                break;
+            }
+            position = next;
+        }
+        self.list_state.select(Some(position));
+    }
+
+    /// The scroll wheel moves through whatever is on screen. Clicking a row in
+    /// the tailnet list selects it, and clicking the selected row switches to it.
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Option<AppAction> {
+        // Popups and forms are keyboard-only
+        let popup = self.show_help
+            || self.show_details
+            || self.form.is_some()
+            || self.flags_form.is_some()
+            || self.confirmation.is_some()
+            || self.rename.is_some();
+        if popup {
+            return None;
+        }
+
+        let delta = match mouse.kind {
+            MouseEventKind::ScrollDown => 1,
+            MouseEventKind::ScrollUp => -1,
+            MouseEventKind::Down(MouseButton::Left) => {
+                let list_shown = self.output_view.is_none()
+                    && self.peers_view.is_none()
+                    && self.exit_node_picker.is_none();
+                return if list_shown {
+                    self.click_list(mouse.column, mouse.row)
+                } else {
+                    None
+                };
+            }
+            _ => return None,
+        };
+
+        if let Some(ref mut output) = self.output_view {
+            output.scroll_by(delta * 3);
+        } else if let Some(ref mut view) = self.peers_view {
+            view.move_selection(delta);
+        } else if let Some(ref mut picker) = self.exit_node_picker {
+            picker.move_selection(delta);
+        } else {
+            let selected = self.list_state.selected().unwrap_or(0);
+            self.select_visible(selected.saturating_add_signed(delta));
+        }
+        None
+    }
+
+    fn click_list(&mut self, column: u16, row: u16) -> Option<AppAction> {
+        let area = self.list_area;
+        let inside =
+            column > area.x && column + 1 < area.right() && row > area.y && row + 1 < area.bottom();
+        if !inside {
+            return None;
+        }
+
+        // Walk down from the first drawn row; profiles with an account take two
+        // lines, group headers one (and can't be clicked)
+        let rows = self.list_rows();
+        let mut line = (row - area.y - 1) as usize;
+        let mut row = self.list_offset;
+        let position = loop {
+            let entry = *rows.get(row)?;
+            let height = entry.map_or(1, |position| {
+                item_height(&self.options[self.visible[position]])
+            });
+            if line < height {
+                break entry?;
+            }
+            line -= height;
+            row += 1;
+        };
+
+        if self.list_state.selected() != Some(position) {
+            self.list_state.select(Some(position));
+            return None;
+        }
+
+        let index = self.selected_option_index()?;
+        let action = self.request_switch(index);
+        self.should_quit = action.is_some();
+        action
+    }
+
+    /// Copy this node's tailscale IP to the clipboard, IPv4 first. `previous`
+    /// is the address copied by an immediately preceding `y`, to cycle on from.
+    fn copy_ip(&mut self, previous: Option<usize>) {
+        let mut ips = self
+            .status
+            .as_ref()
+            .map(|status| status.self_ips.clone())
+            .unwrap_or_default();
+        ips.sort_by_key(|ip| ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()));
+
+        if ips.is_empty() {
+            self.status_message = Some("No tailscale IP to copy (not connected?)".to_string());
+            return;
+        }
+        let index = previous.map_or(0, |i| (i + 1) % ips.len());
+
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    self.status_message = Some(format!("✗ Clipboard unavailable: {}", e));
+                    return;
+                }
+            }
+        }
+
+        if let Some(ref mut clipboard) = self.clipboard {
+            self.status_message = Some(match clipboard.set_text(ips[index].clone()) {
+                Ok(()) if ips.len() > 1 => {
+                    self.copied_ip = Some(index);
+                    format!("✓ Copied {} (y again for the next address)", ips[index])
+                }
+                Ok(()) => format!("✓ Copied {}", ips[index]),
+                Err(e) => format!("✗ Failed to copy: {}", e),
+            });
+        }
+    }
+
+    /// Select the nth visible row, if the list isn't empty
+    fn select_visible(&mut self, position: usize) {
+        if !self.visible.is_empty() {
+            self.list_state
+                .select(Some(position.min(self.visible.len() - 1)));
+        }
+    }
+
+    fn previous(&mut self) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.visible.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    /// Recompute the visible options from the filter, keeping the selection
+    /// on the same option when it still matches
+    fn apply_filter(&mut self) {
+        let previous = self.selected_option_index();
+        let query = self.filter.as_deref().unwrap_or("").to_lowercase();
+
+        self.visible = self
+            .options
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| {
+                option.name.to_lowercase().contains(&query)
+                    || option.label().to_lowercase().contains(&query)
+                    || option
+                        .account
+                        .as_ref()
+                        .is_some_and(|acc| acc.to_lowercase().contains(&query))
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        // Keep each group together, in the order the config lists them
+        let groups = self.config.groups();
+        if !groups.is_empty() {
+            let options = &self.options;
+            self.visible.sort_by_key(|&index| {
+                let group = options[index].group_label();
+                groups
+                    .iter()
+                    .position(|&g| g == group)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+
+        let selected = previous
+            .and_then(|prev| self.visible.iter().position(|&index| index == prev))
+            .or(if self.visible.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+        self.list_state.select(selected);
+    }
+
+    /// The group header shown above the visible row at `position`: at the
+    /// start of each group, once any group is configured
+    fn group_header(&self, position: usize) -> Option<&str> {
+        if self.config.groups().is_empty() {
+            return None;
+        }
+        let group = self.options[*self.visible.get(position)?].group_label();
+        let previous = position
+            .checked_sub(1)
+            .map(|previous| self.options[self.visible[previous]].group_label());
+        (previous != Some(group)).then_some(group)
+    }
+
+    /// The list's rows in display order: a group header (`None`) or a
+    /// position in `visible`
+    fn list_rows(&self) -> Vec<Option<usize>> {
+        let mut rows = Vec::new();
+        for position in 0..self.visible.len() {
+            if self.group_header(position).is_some() {
+                rows.push(None);
+            }
+            rows.push(Some(position));
+        }
+        rows
+    }
+
+    /// Index into `options` of the highlighted row
+    fn selected_option_index(&self) -> Option<usize> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.visible.get(i).copied())
+    }
+
+    /// `tailscale up` for the selected tailnet (or the active one), with its
+    /// flags looked up in the config
+    fn tailscale_up_action(&mut self) -> Option<AppAction> {
+        let Some(name) = self
+            .get_selected_tailnet_name()
+            .or_else(|| self.get_active_tailnet_name())
+        else {
+            self.status_message = Some("✗ No tailnet selected or active".to_string());
+            return None;
+        };
+
+        match self.config.resolve_tailnet(&name) {
+            Ok(tailnet) => Some(AppAction::RunTailscaleUp(tailnet)),
+            Err(e) => {
+                self.status_message = Some(format!("✗ {:#}", e));
+                None
+            }
+        }
+    }
+
+    /// `tailscale up --force-reauth` for the active tailnet. Only the current
+    /// profile can be re-authenticated in place.
+    fn reauthenticate_action(&mut self) -> Option<AppAction> {
+        let Some(name) = self.get_active_tailnet_name() else {
+            self.status_message = Some("✗ Not connected to any tailnet".to_string());
+            return None;
+        };
+
+        match self.config.resolve_tailnet(&name) {
+            Ok(tailnet) => Some(AppAction::Reauthenticate(tailnet)),
+            Err(e) => {
+                self.status_message = Some(format!("✗ {:#}", e));
+                None
+            }
+        }
+    }
+
+    /// Switch to the tailnet at `index` in `options`, asking first if its
+    /// profile is known to be logged out
+    fn request_switch(&mut self, index: usize) -> Option<AppAction> {
+        let option = &self.options[index];
+        if option.is_active {
+            self.status_message = Some(format!("Already connected to {}", option.name));
+            return None;
+        }
+
+        let needs_login = option.needs_login;
+        let tailnet = Tailnet {
+            name: option.name.clone(),
+            profile_id: option.profile_id.clone(),
+            ..Default::default()
+        };
+
+        if needs_login {
+            self.confirmation = Some(Confirmation {
+                message: format!(
+                    "{} is logged out and will require re-authentication. Continue? (y/N)",
+                    tailnet.name
+                ),
+                on_confirm: ConfirmAction::SwitchTailnet(tailnet),
+            });
+            None
+        } else {
+            Some(AppAction::SelectTailnet(tailnet))
+        }
+    }
+
+    pub fn get_selected_tailnet_name(&self) -> Option<String> {
+        self.selected_option_index()
+            .map(|index| self.options[index].name.clone())
+    }
+
+    /// Profile ID (or name, for config entries) of the highlighted row
+    pub fn get_selected_key(&self) -> Option<String> {
+        self.selected_option_index()
+            .map(|index| self.options[index].key().to_string())
+    }
+
+    /// Replace the list options (e.g. after profiles changed), keeping the
+    /// selection on the same profile when it still exists
+    pub fn set_options(&mut self, options: Vec<TailnetOption>) {
+        let selected = self.get_selected_key();
+        self.options = options;
+        self.apply_filter();
+
+        if let Some(key) = selected {
+            self.select_tailnet(&key);
+        }
+    }
+
+    /// Highlight the row for a profile ID or tailnet name. Returns false
+    /// (leaving the selection alone) if it isn't in the visible list.
+    pub fn select_tailnet(&mut self, key: &str) -> bool {
+        let index = self
+            .options
+            .iter()
+            .position(|o| o.key() == key)
+            .or_else(|| self.options.iter().position(|o| o.name == key))
+            .and_then(|index| self.visible.iter().position(|&i| i == index));
+        if index.is_some() {
+            self.list_state.select(index);
+        }
+        index.is_some()
+    }
+
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_message = Some(message);
+    }
+
+    pub fn set_prefs(&mut self, prefs: Option<Prefs>) {
+        self.prefs = prefs;
+    }
+
+    pub fn set_status(&mut self, status: Option<StatusInfo>) {
+        self.status = status;
+    }
+
+    /// Show only the config, refusing anything that needs tailscale
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn set_version(&mut self, version: Option<String>) {
+        self.version = version;
+    }
+
+    pub fn set_previous_tailnet(&mut self, name: Option<String>) {
+        self.previous_tailnet = name;
+    }
+
+    /// Apply status updates sent from a background refresher while running
+    pub fn watch_status(&mut self, updates: Receiver<StatusInfo>) {
+        self.status_updates = Some(updates);
+    }
+
+    fn apply_status(&mut self, status: StatusInfo) {
+        options::mark_active(&mut self.options, &status);
+        self.status = Some(status);
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_active_tailnet_name(&self) -> Option<String> {
+        self.options
+            .iter()
+            .find(|option| option.is_active)
+            .map(|option| option.name.clone())
     }
 
-    fn render_header(&self, f: &mut Frame, area: Rect) {
-        let title = Paragraph::new("TailSwitch - Tailscale Network Switcher")
-            .style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
-        f.render_widget(title, area);
+    pub fn get_active_profile_id(&self) -> Option<String> {
+        self.options
+            .iter()
+            .find(|option| option.is_active)
+            .and_then(|option| option.profile_id.clone())
     }
 
-    fn render_tailnet_list(&mut self, f: &mut Frame, area: Rect) {
-        let items: Vec<ListItem> = self
-            .options
+    /// Show the exit node picker, with the currently used node preselected
+    pub fn show_exit_nodes(&mut self, nodes: Vec<ExitNode>, current: Option<ExitNodeStatus>) {
+        let mut picker = ExitNodePicker {
+            nodes,
+            current,
+            list_state: ListState::default(),
+        };
+        let selected = picker
+            .nodes
             .iter()
-            .map(|(name, account, is_profile, is_active)| {
-                let mut lines = vec![];
-
-                if *is_profile {
-                    // Existing profile - show with checkmark and star if active
-                    let prefix = if *is_active {
-                        Span::styled("★ ", Style::default().fg(Color::Yellow))
-                    } else {
-                        Span::styled("  ", Style::default())
-                    };
+            .position(|node| picker.is_current(node))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        picker.list_state.select(Some(selected));
+        self.exit_node_picker = Some(picker);
+    }
 
-                    lines.push(Line::from(vec![
-                        prefix,
-                        Span::styled(
-                            name,
-                            Style::default()
-                                .fg(Color::White)
-                                .add_modifier(Modifier::BOLD),
-                        ),
-                        if *is_active {
-                            Span::styled(" (active)", Style::default().fg(Color::Green))
-                        } else {
-                            Span::styled("", Style::default())
-                        },
-                    ]));
+    pub fn show_output(&mut self, title: String, content: String) {
+        // Output can echo tailscale commands, which must not show auth keys
+        let content = runner::redact_secrets(&content);
+        self.output_view = Some(OutputView::new(title, content));
+    }
 
-                    if let Some(acc) = account {
-                        lines.push(Line::from(vec![
-                            Span::styled("    ", Style::default()),
-                            Span::styled(acc, Style::default().fg(Color::Gray)),
-                        ]));
-                    }
-                } else {
-                    // New profile from config - show with plus
-                    lines.push(Line::from(vec![
-                        Span::styled("+ ", Style::default().fg(Color::Yellow)),
-                        Span::styled(name, Style::default().fg(Color::White)),
-                        Span::styled(" (add new)", Style::default().fg(Color::DarkGray)),
-                    ]));
-                }
+    /// Write the output view to a new timestamped file in the output
+    /// directory, reporting where it went (or why it failed) in the footer
+    fn save_output(&mut self) {
+        let Some(ref mut output) = self.output_view else {
+            return;
+        };
 
-                ListItem::new(lines)
-            })
-            .collect();
+        let result = self.config.output_dir().and_then(|dir| {
+            let path = dir.join(format!(
+                "tailswitch-{}-{}.txt",
+                slug(&output.title),
+                datetime::compact_utc(std::time::SystemTime::now())
+            ));
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            io::Write::write_all(&mut file, output.content.as_bytes())
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            Ok(path)
+        });
 
-        let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title(
-                "j/k: navigate | Enter: select | u: update flags | s: status | l: logout | q: quit",
-            ))
-            .highlight_style(
-                Style::default()
-                    .bg(Color::Blue)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">> ");
+        output.status_message = Some(match result {
+            Ok(path) => (format!("✓ Saved to {}", path.display()), self.theme.active),
+            Err(e) => (format!("✗ {:#}", e), self.theme.error),
+        });
+    }
 
-        f.render_stateful_widget(list, area, &mut self.list_state);
+    /// Show a running `tailscale ping` in the output view as its lines arrive.
+    /// Leaving the output view stops it.
+    pub fn show_ping(&mut self, peer: &PeerStatus, ping: Streaming) {
+        let target = peer.ips.first().unwrap_or(&peer.hostname);
+        self.show_output(
+            format!("Ping - {}", peer.hostname),
+            format!("Pinging {} ({})…\n\n", peer.hostname, target),
+        );
+        self.ping = Some((peer.hostname.clone(), ping));
     }
 
-    fn render_footer(&self, f: &mut Frame, area: Rect) {
-        let config_path =
-            Config::get_config_path_string().unwrap_or_else(|_| "Unknown".to_string());
-        let footer_text = if let Some(ref msg) = self.status_message {
-            msg.clone()
-        } else {
-            format!("Config: {}", config_path)
+    /// Move new ping output into the output view, and report how it ended
+    fn poll_ping(&mut self) {
+        let Some((ref peer, ref mut ping)) = self.ping else {
+            return;
+        };
+        let Some(ref mut output) = self.output_view else {
+            // Closed with Esc - dropping the ping kills it
+            self.ping = None;
+            return;
         };
 
-        let footer = Paragraph::new(footer_text)
-            .style(Style::default().fg(Color::Gray))
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
-        f.render_widget(footer, area);
-    }
+        let finished = ping.try_finish();
+        let lines = ping.new_lines();
+        if lines.is_empty() && finished.is_none() {
+            return;
+        }
 
-    fn next(&mut self) {
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i >= self.options.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
+        // Keep following the end unless the user scrolled up
+        let following = output.scroll >= output.max_scroll();
+        for line in lines {
+            output.content.push_str(&line);
+            output.content.push('\n');
+        }
+        match finished {
+            Some(_) if ping.timed_out() => output
+                .content
+                .push_str("\n✗ tailscale ping timed out (is tailscaled responding?)"),
+            Some(status) if status.success() => output.content.push_str("\n✓ Done"),
+            // Answered over DERP but never went direct, or similar
+            Some(status) if output.content.contains("pong") => output
+                .content
+                .push_str(&format!("\n✗ tailscale ping failed ({})", status)),
+            Some(_) => output.content.push_str(&format!(
+                "\n✗ No reply from {} - it may be offline or unreachable",
+                peer
+            )),
+            None => {}
+        }
+        if following {
+            output.scroll = usize::MAX; // clamped to the end on the next draw
+        }
+
+        if finished.is_some() {
+            self.ping = None;
+        }
     }
 
-    fn previous(&mut self) {
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.options.len() - 1
-                } else {
-                    i - 1
-                }
-            }
-            None => 0,
+    /// Show the peers of the current tailnet as a table. `raw` is the plain
+    /// `tailscale status` output, shown when toggling with `r`.
+    pub fn show_peers(&mut self, status: &StatusInfo, raw: String) {
+        let mut view = PeersView {
+            summary: status.summary(),
+            peers: status.peers.clone(),
+            raw,
+            sort_column: 0,
+            descending: false,
+            table_state: TableState::default(),
         };
-        self.list_state.select(Some(i));
+        view.apply_sort();
+        if !view.peers.is_empty() {
+            view.table_state.select(Some(0));
+        }
+        self.peers_view = Some(view);
     }
 
-    pub fn get_selected_tailnet_name(&self) -> Option<String> {
-        self.list_state.selected().and_then(|index| {
-            if index < self.options.len() {
-                Some(self.options[index].0.clone())
-            } else {
-                None
+    /// Handle a key in the peers table. Returns an action for pinging or quitting.
+    fn handle_peers_key(&mut self, code: KeyCode) -> Option<AppAction> {
+        let view = self.peers_view.as_mut()?;
+
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => view.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => view.move_selection(-1),
+            KeyCode::PageDown => view.move_selection(10),
+            KeyCode::PageUp => view.move_selection(-10),
+            KeyCode::Char(c @ '1'..='5') => view.sort_by(c as usize - '1' as usize),
+            KeyCode::Char('r') => {
+                let raw = view.raw.clone();
+                self.show_output("Tailscale Status (raw)".to_string(), raw);
             }
-        })
-    }
+            KeyCode::Enter | KeyCode::Char('p') => {
+                let peer = view.peers.get(view.table_state.selected()?)?;
+                return Some(AppAction::Ping(peer.clone()));
+            }
+            KeyCode::Esc => self.peers_view = None,
+            KeyCode::Char('q') => {
+                self.peers_view = None;
+                return Some(AppAction::Quit);
+            }
+            _ => {}
+        }
 
-    pub fn get_active_tailnet_name(&self) -> Option<String> {
-        self.options
-            .iter()
-            .find(|(_, _, _, is_active)| *is_active)
-            .map(|(name, _, _, _)| name.clone())
+        None
     }
 
-    pub fn show_output(&mut self, title: String, content: String) {
-        self.output_view = Some(OutputView { title, content });
+    fn render_peers_view(f: &mut Frame, theme: &Theme, view: &mut PeersView) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(f.area());
+
+        let title = Paragraph::new("Tailscale Status")
+            .style(
+                Style::default()
+                    .fg(theme.header)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(view.summary.as_str());
+
+        if view.peers.is_empty() {
+            let empty = Paragraph::new("No peers in this tailnet")
+                .style(Style::default().fg(theme.dim))
+                .alignment(Alignment::Center)
+                .block(block);
+            f.render_widget(empty, chunks[1]);
+        } else {
+            let header = Row::new(PEER_COLUMNS.iter().enumerate().map(|(i, name)| {
+                let label = if i == view.sort_column {
+                    format!("{} {}", name, if view.descending { "▼" } else { "▲" })
+                } else {
+                    name.to_string()
+                };
+                Cell::from(label)
+            }))
+            .style(
+                Style::default()
+                    .fg(theme.warning)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+            let rows = view.peers.iter().map(|peer| {
+                let (state, color) = if peer.online {
+                    ("● online", theme.active)
+                } else {
+                    ("○ offline", theme.dim)
+                };
+                let connection = match peer.connection {
+                    Some(ref connection @ PeerConnection::Direct) => {
+                        Span::styled(connection.to_string(), Style::default().fg(theme.active))
+                    }
+                    Some(ref connection @ PeerConnection::Relayed(_)) => {
+                        Span::styled(connection.to_string(), Style::default().fg(theme.warning))
+                    }
+                    None => Span::styled("-", Style::default().fg(theme.dim)),
+                };
+                Row::new(vec![
+                    Cell::from(peer.hostname.as_str()),
+                    Cell::from(peer.ips.first().map(String::as_str).unwrap_or("-")),
+                    Cell::from(peer.os.as_str()),
+                    Cell::from(Span::styled(state, Style::default().fg(color))),
+                    Cell::from(connection),
+                ])
+            });
+
+            let table = Table::new(
+                rows,
+                [
+                    Constraint::Percentage(40),
+                    Constraint::Length(18),
+                    Constraint::Length(10),
+                    Constraint::Length(10),
+                    Constraint::Length(14),
+                ],
+            )
+            .header(header)
+            .block(block)
+            .row_highlight_style(theme.selection.add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+            f.render_stateful_widget(table, chunks[1], &mut view.table_state);
+        }
+
+        let footer = Paragraph::new(
+            "j/k: scroll | 1-5: sort by column | Enter/p: ping | r: raw output | Esc: go back | q: quit",
+        )
+        .style(Style::default().fg(theme.muted))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
     }
 
-    fn render_output_view(&self, f: &mut Frame, output: &OutputView) {
+    fn render_output_view(f: &mut Frame, theme: &Theme, output: &mut OutputView) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(2)
@@ -337,7 +2818,7 @@ impl App {
         let title = Paragraph::new(output.title.as_str())
             .style(
                 Style::default()
-                    .fg(Color::Cyan)
+                    .fg(theme.header)
                     .add_modifier(Modifier::BOLD),
             )
             .alignment(Alignment::Center)
@@ -345,75 +2826,378 @@ impl App {
         f.render_widget(title, chunks[0]);
 
         // Content
+        let block = Block::default().borders(Borders::ALL).title("Output");
+        let inner = block.inner(chunks[1]);
+        let width = inner.width.max(1) as usize;
+
+        // Approximate the wrapped height so scrolling stops at the end
+        output.total_lines = output
+            .content
+            .lines()
+            .map(|line| line.chars().count().div_ceil(width).max(1))
+            .sum();
+        output.viewport_height = inner.height as usize;
+        output.scroll = output.scroll.min(output.max_scroll());
+
         let content = Paragraph::new(output.content.as_str())
-            .style(Style::default().fg(Color::White))
-            .block(Block::default().borders(Borders::ALL).title("Output"))
-            .wrap(ratatui::widgets::Wrap { trim: false });
+            .style(Style::default().fg(theme.text))
+            .block(block)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .scroll((output.scroll as u16, 0));
         f.render_widget(content, chunks[1]);
 
+        if output.max_scroll() > 0 {
+            let mut scrollbar_state =
+                ScrollbarState::new(output.max_scroll()).position(output.scroll);
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                chunks[1],
+                &mut scrollbar_state,
+            );
+        }
+
         // Footer
-        let footer = Paragraph::new("Press Enter or Esc to go back | q to quit")
-            .style(Style::default().fg(Color::Gray))
+        let (hint, color) = match output.status_message {
+            Some((ref message, color)) => (message.as_str(), color),
+            None => (
+                "j/k/PgUp/PgDn: scroll | w: save to file | Enter or Esc: go back | q: quit",
+                theme.muted,
+            ),
+        };
+        let footer = Paragraph::new(hint)
+            .style(Style::default().fg(color))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(footer, chunks[2]);
     }
 }
 
+/// Keybindings for one screen, as (keys, description)
+type HelpEntries = &'static [(&'static str, &'static str)];
+
+const LIST_HELP: HelpEntries = &[
+    ("j/k, ↑/↓", "Move the selection"),
+    ("gg/G", "Jump to the first/last tailnet"),
+    ("PgUp/PgDn", "Move a screenful"),
+    (".", "Jump to the active tailnet"),
+    ("Enter", "Switch to the selected tailnet"),
+    ("1-9", "Switch to the numbered tailnet"),
+    ("p", "Switch back to the previous tailnet"),
+    ("/", "Filter by name or account"),
+    ("a/e/d", "Add, edit or delete a tailnet"),
+    ("N", "Rename the selected profile"),
+    ("f", "Toggle common flags"),
+    ("i", "Show the selected tailnet's config"),
+    ("u", "Apply configured flags (tailscale up)"),
+    ("R", "Re-authenticate the active tailnet (force-reauth)"),
+    ("x", "Pick an exit node"),
+    ("A/D", "Toggle accept-routes / accept-dns"),
+    ("y", "Copy this node's IP (again for the next one)"),
+    ("s", "Show status and peers"),
+    ("n", "Run tailscale netcheck"),
+    ("r", "Refresh profiles"),
+    ("l", "Log out"),
+    ("?", "Toggle this help"),
+    ("q", "Quit"),
+];
+
+const OUTPUT_HELP: HelpEntries = &[
+    ("j/k, ↑/↓", "Scroll"),
+    ("PgUp/PgDn", "Scroll a page"),
+    ("w", "Save the output to a file"),
+    ("Enter/Esc", "Go back (stops a running ping)"),
+    ("?", "Toggle this help"),
+    ("q", "Quit"),
+];
+
+const PEERS_HELP: HelpEntries = &[
+    ("j/k, ↑/↓", "Move through the peers"),
+    ("1-5", "Sort by column (again to reverse)"),
+    ("Enter/p", "Ping the selected peer"),
+    ("r", "Toggle the raw tailscale status output"),
+    ("Esc", "Go back"),
+    ("?", "Toggle this help"),
+    ("q", "Quit"),
+];
+
+const EXIT_NODE_HELP: HelpEntries = &[
+    ("j/k, ↑/↓", "Move the selection"),
+    ("Enter", "Use the selected exit node"),
+    ("Esc", "Go back"),
+    ("?", "Toggle this help"),
+    ("q", "Quit"),
+];
+
+const URL_HELP: HelpEntries = &[
+    ("Enter", "Open the URL in your browser"),
+    ("c", "Copy the URL"),
+    ("Q", "Toggle a QR code of the URL"),
+    ("q/Esc", "Exit, leaving the login waiting"),
+    ("x", "Cancel the login"),
+    ("?", "Toggle this help"),
+];
+
+/// Centered popup listing the keybindings for the current screen
+fn render_help(f: &mut Frame, theme: &Theme, title: &str, entries: HelpEntries) {
+    let key_width = entries
+        .iter()
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![Line::from("")];
+    lines.extend(entries.iter().map(|(keys, description)| {
+        Line::from(vec![
+            Span::styled(
+                format!("  {:>width$}  ", keys, width = key_width),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(*description, Style::default().fg(theme.text)),
+        ])
+    }));
+
+    let area = centered_rect(60, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.accent))
+            .title(format!("Help - {}", title))
+            .title_bottom("Press any key to close"),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Lines a row takes up in the tailnet list (profiles show their account below)
+fn item_height(option: &TailnetOption) -> usize {
+    if option.is_profile && option.account.is_some() {
+        2
+    } else {
+        1
+    }
+}
+
+/// The longest run of `chars` that fits in `width` columns
+fn fit(chars: impl Iterator<Item = char>, width: usize) -> String {
+    let mut used = 0;
+    chars
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
+
+/// `text` cut to `width` columns, ending in "…" when it doesn't fit
+fn truncate(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        Cow::Borrowed(text)
+    } else if width == 0 {
+        Cow::Owned(String::new())
+    } else {
+        Cow::Owned(format!("{}…", fit(text.chars(), width - 1)))
+    }
+}
+
+/// `text` cut to `width` columns by replacing its middle with "…", for URLs
+/// whose start (the server) and end (the key) both matter
+fn truncate_middle(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width || width < 3 {
+        return truncate(text, width);
+    }
+    let head = fit(text.chars(), (width - 1) / 2);
+    let tail = fit(text.chars().rev(), width - 1 - head.width());
+    Cow::Owned(format!(
+        "{}…{}",
+        head,
+        tail.chars().rev().collect::<String>()
+    ))
+}
+
+/// Sort key for the peers table's Connection column
+fn connection_rank(peer: &PeerStatus) -> u8 {
+    match peer.connection {
+        Some(PeerConnection::Direct) => 0,
+        Some(PeerConnection::Relayed(_)) => 1,
+        None => 2,
+    }
+}
+
+/// A title as a file name part, e.g. "Tailscale Up - work" -> "tailscale-up-work"
+fn slug(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A rectangle of the given width percentage and height, centered in `area`
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Fill(1),
+            Constraint::Length(height.min(area.height)),
+            Constraint::Fill(1),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Whether the terminal is known to render OSC 8 hyperlinks. Unknown
+/// terminals, tmux and screen (which may not pass them through) get plain
+/// text; `TAILSWITCH_HYPERLINKS=1` or `0` overrides the guess.
+fn hyperlinks_supported() -> bool {
+    if let Ok(value) = std::env::var("TAILSWITCH_HYPERLINKS") {
+        return value == "1";
+    }
+    let var = |name| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    if std::env::var_os("TMUX").is_some() || term.starts_with("screen") {
+        return false;
+    }
+    matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty"
+    ) || term == "xterm-kitty"
+        || term.starts_with("foot")
+        || std::env::var_os("WT_SESSION").is_some()
+        || var("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5000)
+}
+
+/// What the user chose on the auth URL screen
+pub enum UrlAction {
+    OpenBrowser,
+    /// Leave the login running so the URL can still be used
+    Leave,
+    /// Stop the background login
+    Cancel,
+}
+
 pub struct UrlDisplayApp {
     url: String,
     tailnet_name: String,
     should_quit: bool,
-    open_browser: bool,
+    action: UrlAction,
+    status_message: Option<(String, Color)>,
+    show_qr: bool,
+    show_help: bool,
+    theme: Theme,
+    url_area: Rect, // where the URL box was last drawn, for mouse clicks
+    hyperlinks: bool,
+    // Kept alive so the copied text stays available on X11/Wayland
+    clipboard: Option<arboard::Clipboard>,
 }
 
 impl UrlDisplayApp {
-    pub fn new(url: String, tailnet_name: String) -> Self {
+    pub fn new(url: String, tailnet_name: String, theme: Theme) -> Self {
         Self {
             url,
             tailnet_name,
             should_quit: false,
-            open_browser: false,
+            action: UrlAction::Leave,
+            status_message: None,
+            show_qr: false,
+            show_help: false,
+            theme,
+            url_area: Rect::default(),
+            hyperlinks: hyperlinks_supported(),
+            clipboard: None,
         }
     }
 
-    pub fn run(&mut self) -> Result<bool> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+    fn copy_url(&mut self) {
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    self.status_message =
+                        Some((format!("✗ Clipboard unavailable: {}", e), self.theme.error));
+                    return;
+                }
+            }
+        }
 
-        let result = self.run_loop(&mut terminal);
+        if let Some(ref mut clipboard) = self.clipboard {
+            self.status_message = match clipboard.set_text(self.url.clone()) {
+                Ok(()) => Some(("✓ Copied!".to_string(), self.theme.active)),
+                Err(e) => Some((format!("✗ Failed to copy: {}", e), self.theme.error)),
+            };
+        }
+    }
 
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-        terminal.show_cursor()?;
+    pub fn run(self) -> Result<UrlAction> {
+        // Setup terminal, restored when the guard drops (even on `?` or panic)
+        let _guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
 
-        result
+        self.run_loop(&mut terminal)
     }
 
-    fn run_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<bool> {
+    fn run_loop(
+        mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> Result<UrlAction> {
         loop {
-            terminal.draw(|f| self.ui(f))?;
+            terminal.draw(|f| {
+                self.ui(f);
+            })?;
+
+            let event = event::read()?;
 
-            if let Event::Key(key) = event::read()?
+            // Clicking the URL opens it, like Enter
+            if let Event::Mouse(mouse) = event
+                && mouse.kind == MouseEventKind::Down(MouseButton::Left)
+                && !self.show_help
+                && !self.show_qr
+                && self.url_area.contains((mouse.column, mouse.row).into())
+            {
+                self.action = UrlAction::OpenBrowser;
+                self.should_quit = true;
+            }
+
+            if let Event::Key(key) = event
                 && key.kind == KeyEventKind::Press
             {
+                if self.show_help {
+                    // Any key closes the help
+                    self.show_help = false;
+                    continue;
+                }
+
                 match key.code {
+                    KeyCode::Char('?') => {
+                        self.show_help = true;
+                    }
                     KeyCode::Enter => {
-                        self.open_browser = true;
+                        self.action = UrlAction::OpenBrowser;
                         self.should_quit = true;
                     }
                     KeyCode::Char('q') | KeyCode::Esc => {
                         self.should_quit = true;
                     }
-                    KeyCode::Char('c') => {
-                        // Future: copy to clipboard
+                    KeyCode::Char('x') => {
+                        self.action = UrlAction::Cancel;
                         self.should_quit = true;
                     }
+                    KeyCode::Char('c') => {
+                        self.copy_url();
+                    }
+                    KeyCode::Char('Q') => {
+                        self.show_qr = !self.show_qr;
+                    }
                     _ => {}
                 }
             }
@@ -423,7 +3207,7 @@ impl UrlDisplayApp {
             }
         }
 
-        Ok(self.open_browser)
+        Ok(self.action)
     }
 
     fn ui(&mut self, f: &mut Frame) {
@@ -440,13 +3224,17 @@ impl UrlDisplayApp {
         self.render_header(f, chunks[0]);
         self.render_url_box(f, chunks[1]);
         self.render_instructions(f, chunks[2]);
+
+        if self.show_help {
+            render_help(f, &self.theme, "Authentication", URL_HELP);
+        }
     }
 
     fn render_header(&self, f: &mut Frame, area: Rect) {
         let title = Paragraph::new(format!("Authentication Required - {}", self.tailnet_name))
             .style(
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.warning)
                     .add_modifier(Modifier::BOLD),
             )
             .alignment(Alignment::Center)
@@ -454,40 +3242,59 @@ impl UrlDisplayApp {
         f.render_widget(title, area);
     }
 
-    fn render_url_box(&self, f: &mut Frame, area: Rect) {
+    fn render_url_box(&mut self, f: &mut Frame, area: Rect) {
+        self.url_area = area;
+        if self.show_qr {
+            self.render_qr_code(f, area);
+            return;
+        }
+
+        // Long URLs (e.g. Headscale's) lose their middle; `c`, Enter and the
+        // QR code still use the whole thing
+        let inner = area.inner(Margin::new(1, 1));
+        let shown = truncate_middle(&self.url, inner.width as usize);
+        let shortened = if matches!(shown, Cow::Owned(_)) {
+            Line::from(Span::styled(
+                "(shortened - press c to copy the full URL)",
+                Style::default().fg(self.theme.dim),
+            ))
+        } else {
+            Line::from("")
+        };
+
         let text = vec![
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Authentication URL:",
                 Style::default()
-                    .fg(Color::Cyan)
+                    .fg(self.theme.accent)
                     .add_modifier(Modifier::BOLD),
             )]),
             Line::from(""),
             Line::from(vec![Span::styled(
-                &self.url,
-                Style::default().fg(Color::Green),
+                shown.as_ref(),
+                Style::default().fg(self.theme.active),
             )]),
-            Line::from(""),
+            shortened,
             Line::from(""),
             Line::from(vec![
                 Span::styled(
                     "Please authenticate in your browser and select the ",
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(self.theme.muted),
                 ),
                 Span::styled(
                     &self.tailnet_name,
                     Style::default()
-                        .fg(Color::White)
+                        .fg(self.theme.text)
                         .add_modifier(Modifier::BOLD),
                 ),
-                Span::styled(" tailnet.", Style::default().fg(Color::Gray)),
+                Span::styled(" tailnet.", Style::default().fg(self.theme.muted)),
             ]),
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Tailscale is running in the background waiting for authentication...",
                 Style::default()
-                    .fg(Color::DarkGray)
+                    .fg(self.theme.dim)
                     .add_modifier(Modifier::ITALIC),
             )]),
         ];
@@ -496,26 +3303,160 @@ impl UrlDisplayApp {
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL).title("URL"));
         f.render_widget(paragraph, area);
+
+        if self.hyperlinks {
+            self.link_url(f, inner, &shown);
+        }
+    }
+
+    /// Turn the drawn URL (`shown`, the centered fourth line of `inner`) into
+    /// an OSC 8 hyperlink to the full URL. Each cell holds one escape-wrapped
+    /// two-character chunk, with the cell after it skipped, so ratatui's width
+    /// bookkeeping stays intact.
+    fn link_url(&self, f: &mut Frame, inner: Rect, shown: &str) {
+        let url = &self.url;
+        let shown: Vec<char> = shown.chars().collect();
+        // Anything else could smuggle escape sequences, or is cropped
+        if !url.bytes().all(|b| b.is_ascii_graphic()) || shown.len() > inner.width as usize {
+            return;
+        }
+        if inner.height <= 3 {
+            return;
+        }
+
+        let x = inner.x + (inner.width - shown.len() as u16) / 2;
+        let y = inner.y + 3;
+        let buffer = f.buffer_mut();
+        for (i, chunk) in shown.chunks(2).enumerate() {
+            let chunk: String = chunk.iter().collect();
+            let cell_x = x + 2 * i as u16;
+            buffer[(cell_x, y)].set_symbol(&format!("\x1b]8;;{url}\x1b\\{chunk}\x1b]8;;\x1b\\"));
+            if chunk.chars().count() == 2 {
+                buffer[(cell_x + 1, y)].set_skip(true);
+            }
+        }
+    }
+
+    fn render_qr_code(&self, f: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title("QR Code");
+        let inner = block.inner(area);
+
+        let qr = match QrCode::new(self.url.as_bytes()) {
+            Ok(code) => code
+                .render::<Dense1x2>()
+                // Swap colors so the code scans on dark terminal backgrounds
+                .dark_color(Dense1x2::Light)
+                .light_color(Dense1x2::Dark)
+                .build(),
+            Err(e) => {
+                let paragraph = Paragraph::new(format!("Unable to encode URL as QR code: {}", e))
+                    .style(Style::default().fg(self.theme.error))
+                    .alignment(Alignment::Center)
+                    .block(block);
+                f.render_widget(paragraph, area);
+                return;
+            }
+        };
+
+        let qr_width = qr.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let qr_height = qr.lines().count();
+
+        // A clipped QR code won't scan, so ask for a bigger terminal instead
+        if qr_width > inner.width as usize || qr_height > inner.height as usize {
+            let paragraph = Paragraph::new(vec![
+                Line::from(""),
+                Line::from(Span::styled(
+                    format!(
+                        "Terminal too small for QR code (needs {}x{}, have {}x{})",
+                        qr_width, qr_height, inner.width, inner.height
+                    ),
+                    Style::default().fg(self.theme.warning),
+                )),
+                Line::from(Span::styled(
+                    "Resize the window or press Q to show the URL",
+                    Style::default().fg(self.theme.muted),
+                )),
+            ])
+            .alignment(Alignment::Center)
+            .block(block);
+            f.render_widget(paragraph, area);
+            return;
+        }
+
+        let lines: Vec<Line> = qr
+            .lines()
+            .map(|l| Line::from(Span::styled(l.to_string(), self.theme.qr)))
+            .collect();
+
+        let paragraph = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(paragraph, area);
     }
 
     fn render_instructions(&self, f: &mut Frame, area: Rect) {
         let text = vec![
             Line::from(""),
             Line::from(vec![
-                Span::styled("Press ", Style::default().fg(Color::Gray)),
+                Span::styled("Press ", Style::default().fg(self.theme.muted)),
                 Span::styled(
                     "Enter",
                     Style::default()
-                        .fg(Color::Green)
+                        .fg(self.theme.active)
                         .add_modifier(Modifier::BOLD),
                 ),
-                Span::styled(" to open browser  |  ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    " to open browser  |  ",
+                    Style::default().fg(self.theme.muted),
+                ),
                 Span::styled(
                     "q",
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    Style::default()
+                        .fg(self.theme.error)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    " to exit without opening  |  ",
+                    Style::default().fg(self.theme.muted),
+                ),
+                Span::styled(
+                    "x",
+                    Style::default()
+                        .fg(self.theme.error)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    " to cancel login  |  ",
+                    Style::default().fg(self.theme.muted),
+                ),
+                Span::styled(
+                    "c",
+                    Style::default()
+                        .fg(self.theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(" to copy URL  |  ", Style::default().fg(self.theme.muted)),
+                Span::styled(
+                    "Q",
+                    Style::default()
+                        .fg(self.theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(" for QR code  |  ", Style::default().fg(self.theme.muted)),
+                Span::styled(
+                    "?",
+                    Style::default()
+                        .fg(self.theme.accent)
+                        .add_modifier(Modifier::BOLD),
                 ),
-                Span::styled(" to exit without opening", Style::default().fg(Color::Gray)),
+                Span::styled(" for help", Style::default().fg(self.theme.muted)),
             ]),
+            match self.status_message {
+                Some((ref msg, color)) => {
+                    Line::from(Span::styled(msg.as_str(), Style::default().fg(color)))
+                }
+                None => Line::from(""),
+            },
         ];
 
         let paragraph = Paragraph::new(text)